@@ -0,0 +1,208 @@
+// Generated by compiler_utils, LALR table, from grammar 75d59aaf977dbf5f; do not edit.
+
+pub static SYMBOLS: &[&str] = &[
+    "$",
+    "(",
+    ")",
+    "*",
+    "+",
+    "NUM",
+    "Program",
+    "Expr",
+    "Term",
+    "Factor",
+];
+
+// (lhs, rhs_id, rhs) by production index
+static PRODUCTIONS: &[(usize, u32, &[usize])] = &[
+    (6, 0, &[7, 0]), // Program -> Expr $
+    (7, 0, &[7, 4, 8]), // Expr -> Expr + Term
+    (7, 1, &[8]), // Expr -> Term
+    (8, 0, &[8, 3, 9]), // Term -> Term * Factor
+    (8, 1, &[9]), // Term -> Factor
+    (9, 0, &[1, 7, 2]), // Factor -> ( Expr )
+    (9, 1, &[5]), // Factor -> NUM
+];
+
+// Some((repeat, element)) for the Repeat and Optional helpers, by symbol
+static LISTS: &[Option<(bool, usize)>] = &[
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree
+{
+    // a non-terminal with the production it was expanded by; Repeat and Optional helpers
+    // are a single node, rhs_id 0, with one child per element
+    Node
+    {
+        symbol: &'static str,
+        rhs_id: u32,
+        children: Vec<Tree>
+    },
+    // a terminal with its text and index in the tokens
+    Leaf
+    {
+        symbol: &'static str,
+        text: String,
+        position: usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError
+{
+    // index of the offending token, the number of tokens at end of input
+    pub position: usize,
+    pub message: String
+}
+
+enum Slot
+{
+    Tree(Tree),
+    List(Vec<Tree>)
+}
+
+fn finish(symbol: usize, slot: Slot) -> Tree
+{
+    match slot
+    {
+        Slot::Tree(tree) => tree,
+        Slot::List(children) => Tree::Node { symbol: SYMBOLS[symbol], rhs_id: 0, children }
+    }
+}
+
+fn reduce(production: usize, children: Vec<Slot>) -> Slot
+{
+    let (lhs, rhs_id, rhs) = PRODUCTIONS[production];
+    match LISTS[lhs]
+    {
+        Some((repeat, element)) => {
+            let mut elements = Vec::<Tree>::new();
+            for (symbol, slot) in rhs.iter().zip(children)
+            {
+                match slot
+                {
+                    // the recursion of X* or X+ continues the same list
+                    Slot::List(mut list) if repeat && LISTS[*symbol] == Some((true, element)) => elements.append(&mut list),
+                    slot => elements.push(finish(*symbol, slot))
+                }
+            }
+            Slot::List(elements)
+        },
+        None => Slot::Tree(Tree::Node
+        {
+            symbol: SYMBOLS[lhs],
+            rhs_id,
+            children: rhs.iter().zip(children).map(|(symbol, slot)| finish(*symbol, slot)).collect()
+        })
+    }
+}
+
+fn unexpected(tokens: &[(&str, &str)], position: usize) -> ParseError
+{
+    let message = match tokens.get(position)
+    {
+        Some((kind, _)) => format!("Unexpected token {}.", kind),
+        None => String::from("Unexpected end of file.")
+    };
+    ParseError { position, message }
+}
+
+fn leaf(tokens: &[(&str, &str)], position: usize, symbol: usize) -> Slot
+{
+    Slot::Tree(Tree::Leaf { symbol: SYMBOLS[symbol], text: tokens[position].1.to_string(), position })
+}
+
+fn terminal(kind: &str) -> Option<usize>
+{
+    match kind
+    {
+        "$" => Some(0),
+        "(" => Some(1),
+        ")" => Some(2),
+        "*" => Some(3),
+        "+" => Some(4),
+        "NUM" => Some(5),
+        _ => None
+    }
+}
+
+const ACCEPT: i32 = i32::MAX;
+
+// by state, then 0 for end of input or symbol + 1: 0 is an error, n > 0 shifts to state n - 1,
+// n < 0 reduces production -n - 1
+static ACTION: &[&[i32]] = &[
+    &[0, 0, 2, 0, 0, 0, 5, ACCEPT, 3, 6, 4],
+    &[0, 0, 2, 0, 0, 0, 5, 0, 12, 6, 4],
+    &[0, 9, 0, 0, 0, 10, 0, 0, 0, 0, 0],
+    &[0, -5, 0, -5, -5, -5, 0, 0, 0, 0, 0],
+    &[0, -7, 0, -7, -7, -7, 0, 0, 0, 0, 0],
+    &[0, -3, 0, -3, 7, -3, 0, 0, 0, 0, 0],
+    &[0, 0, 2, 0, 0, 0, 5, 0, 0, 0, 8],
+    &[0, -4, 0, -4, -4, -4, 0, 0, 0, 0, 0],
+    &[-1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    &[0, 0, 2, 0, 0, 0, 5, 0, 0, 11, 4],
+    &[0, -2, 0, -2, 7, -2, 0, 0, 0, 0, 0],
+    &[0, 0, 0, 13, 0, 10, 0, 0, 0, 0, 0],
+    &[0, -6, 0, -6, -6, -6, 0, 0, 0, 0, 0],
+];
+
+pub fn parse(tokens: &[(&str, &str)]) -> Result<Tree, ParseError>
+{
+    // (state, value) pairs
+    let mut stack = Vec::<(usize, Slot)>::new();
+    let mut position = 0;
+    // the non-terminal produced by the last reduction, consumed before any further input
+    let mut reduced: Option<(usize, Slot)> = None;
+
+    loop
+    {
+        let state = stack.last().map_or(0, |(state, _)| *state);
+        let column = match (&reduced, tokens.get(position))
+        {
+            (Some((symbol, _)), _) => symbol + 1,
+            (None, Some((kind, _))) => terminal(kind).ok_or_else(|| unexpected(tokens, position))? + 1,
+            (None, None) => 0
+        };
+
+        match ACTION[state][column]
+        {
+            0 => return Err(unexpected(tokens, position)),
+            ACCEPT => {
+                if position < tokens.len()
+                {
+                    return Err(ParseError { position, message: format!("Unexpected token {} after the end of the parse.", tokens[position].0) });
+                }
+                let (symbol, slot) = reduced.take().unwrap();
+                return Ok(finish(symbol, slot));
+            },
+            action if action > 0 => {
+                let slot = match reduced.take()
+                {
+                    Some((_, slot)) => slot,
+                    None => {
+                        position += 1;
+                        leaf(tokens, position - 1, column - 1)
+                    }
+                };
+                stack.push((action as usize - 1, slot));
+            },
+            action => {
+                let production = (-action - 1) as usize;
+                let (lhs, _, rhs) = PRODUCTIONS[production];
+                let children = stack.split_off(stack.len() - rhs.len()).into_iter().map(|(_, slot)| slot).collect();
+                reduced = Some((lhs, reduce(production, children)));
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+
+fn text(tree: &Tree) -> String
+{
+    match tree
+    {
+        Tree::Leaf { text, .. } => text.clone(),
+        Tree::Node { .. } => panic!("a terminal was expected")
+    }
+}
+
+fn children(tree: &Tree) -> &[Tree]
+{
+    match tree
+    {
+        Tree::Node { children, .. } => children,
+        Tree::Leaf { .. } => &[]
+    }
+}
+
+fn rhs_id(tree: &Tree) -> u32
+{
+    match tree
+    {
+        Tree::Node { rhs_id, .. } => *rhs_id,
+        Tree::Leaf { .. } => 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Group1
+{
+    // (, Item) -> , Item
+    Rhs0(String, Box<Item>),
+}
+
+impl Group1
+{
+    pub fn from_tree(tree: &Tree) -> Group1
+    {
+        Group1::Rhs0(text(&children(tree)[0]), Box::new(Item::from_tree(&children(tree)[1])))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Group2
+{
+    // (Item (, Item)*) -> Item (, Item)*
+    Rhs0(Box<Item>, Vec<Group1>),
+}
+
+impl Group2
+{
+    pub fn from_tree(tree: &Tree) -> Group2
+    {
+        Group2::Rhs0(Box::new(Item::from_tree(&children(tree)[0])), children(&children(tree)[1]).iter().map(Group1::from_tree).collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Start
+{
+    // Start -> List $
+    Rhs0(Box<List>, String),
+}
+
+impl Start
+{
+    pub fn from_tree(tree: &Tree) -> Start
+    {
+        Start::Rhs0(Box::new(List::from_tree(&children(tree)[0])), text(&children(tree)[1]))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum List
+{
+    // List -> [ (Item (, Item)*)? ]
+    Rhs0(String, Option<Box<Group2>>, String),
+}
+
+impl List
+{
+    pub fn from_tree(tree: &Tree) -> List
+    {
+        List::Rhs0(text(&children(tree)[0]), children(&children(tree)[1]).first().map(|tree| Box::new(Group2::from_tree(tree))), text(&children(tree)[2]))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum Item
+{
+    // Item -> num
+    Rhs0(String),
+    // Item -> < num+ >
+    Rhs1(String, Vec<String>, String),
+    // Item -> List
+    Rhs2(Box<List>),
+}
+
+impl Item
+{
+    pub fn from_tree(tree: &Tree) -> Item
+    {
+        match rhs_id(tree)
+        {
+            0 => Item::Rhs0(text(&children(tree)[0])),
+            1 => Item::Rhs1(text(&children(tree)[0]), children(&children(tree)[1]).iter().map(text).collect(), text(&children(tree)[2])),
+            _ => Item::Rhs2(Box::new(List::from_tree(&children(tree)[0])))
+        }
+    }
+}
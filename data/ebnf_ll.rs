@@ -0,0 +1,275 @@
+// Generated by compiler_utils, LL(1) recursive descent, from grammar a5138bb4ef02ab7c; do not edit.
+
+pub static SYMBOLS: &[&str] = &[
+    "$",
+    ",",
+    "<",
+    ">",
+    "[",
+    "]",
+    "num",
+    "(, Item)",
+    "(, Item)*",
+    "(Item (, Item)*)",
+    "(Item (, Item)*)?",
+    "num+",
+    "Start",
+    "List",
+    "Item",
+    "num*",
+];
+
+// (lhs, rhs_id, rhs) by production index
+static PRODUCTIONS: &[(usize, u32, &[usize])] = &[
+    (7, 0, &[1, 14]), // (, Item) -> , Item
+    (8, 0, &[7, 8]), // (, Item)* -> (, Item) (, Item)*
+    (8, 1, &[]), // (, Item)* ->
+    (9, 0, &[14, 8]), // (Item (, Item)*) -> Item (, Item)*
+    (10, 0, &[9]), // (Item (, Item)*)? -> (Item (, Item)*)
+    (10, 1, &[]), // (Item (, Item)*)? ->
+    (11, 0, &[6, 15]), // num+ -> num num*
+    (12, 0, &[13, 0]), // Start -> List $
+    (13, 0, &[4, 10, 5]), // List -> [ (Item (, Item)*)? ]
+    (14, 0, &[6]), // Item -> num
+    (14, 1, &[2, 11, 3]), // Item -> < num+ >
+    (14, 2, &[13]), // Item -> List
+    (15, 0, &[6, 15]), // num* -> num num*
+    (15, 1, &[]), // num* ->
+];
+
+// Some((repeat, element)) for the Repeat and Optional helpers, by symbol
+static LISTS: &[Option<(bool, usize)>] = &[
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    None,
+    Some((true, 7)),
+    None,
+    Some((false, 9)),
+    Some((true, 6)),
+    None,
+    None,
+    None,
+    Some((true, 6)),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree
+{
+    // a non-terminal with the production it was expanded by; Repeat and Optional helpers
+    // are a single node, rhs_id 0, with one child per element
+    Node
+    {
+        symbol: &'static str,
+        rhs_id: u32,
+        children: Vec<Tree>
+    },
+    // a terminal with its text and index in the tokens
+    Leaf
+    {
+        symbol: &'static str,
+        text: String,
+        position: usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError
+{
+    // index of the offending token, the number of tokens at end of input
+    pub position: usize,
+    pub message: String
+}
+
+enum Slot
+{
+    Tree(Tree),
+    List(Vec<Tree>)
+}
+
+fn finish(symbol: usize, slot: Slot) -> Tree
+{
+    match slot
+    {
+        Slot::Tree(tree) => tree,
+        Slot::List(children) => Tree::Node { symbol: SYMBOLS[symbol], rhs_id: 0, children }
+    }
+}
+
+fn reduce(production: usize, children: Vec<Slot>) -> Slot
+{
+    let (lhs, rhs_id, rhs) = PRODUCTIONS[production];
+    match LISTS[lhs]
+    {
+        Some((repeat, element)) => {
+            let mut elements = Vec::<Tree>::new();
+            for (symbol, slot) in rhs.iter().zip(children)
+            {
+                match slot
+                {
+                    // the recursion of X* or X+ continues the same list
+                    Slot::List(mut list) if repeat && LISTS[*symbol] == Some((true, element)) => elements.append(&mut list),
+                    slot => elements.push(finish(*symbol, slot))
+                }
+            }
+            Slot::List(elements)
+        },
+        None => Slot::Tree(Tree::Node
+        {
+            symbol: SYMBOLS[lhs],
+            rhs_id,
+            children: rhs.iter().zip(children).map(|(symbol, slot)| finish(*symbol, slot)).collect()
+        })
+    }
+}
+
+fn unexpected(tokens: &[(&str, &str)], position: usize) -> ParseError
+{
+    let message = match tokens.get(position)
+    {
+        Some((kind, _)) => format!("Unexpected token {}.", kind),
+        None => String::from("Unexpected end of file.")
+    };
+    ParseError { position, message }
+}
+
+fn leaf(tokens: &[(&str, &str)], position: usize, symbol: usize) -> Slot
+{
+    Slot::Tree(Tree::Leaf { symbol: SYMBOLS[symbol], text: tokens[position].1.to_string(), position })
+}
+
+const START: usize = 12;
+
+pub fn parse(tokens: &[(&str, &str)]) -> Result<Tree, ParseError>
+{
+    let mut parser = Parser { tokens, position: 0 };
+    let slot = parser.nonterminal_12()?;
+    if parser.position < tokens.len()
+    {
+        return Err(ParseError { position: parser.position, message: format!("Unexpected token {} after the end of the parse.", tokens[parser.position].0) });
+    }
+    Ok(finish(START, slot))
+}
+
+struct Parser<'a>
+{
+    tokens: &'a [(&'a str, &'a str)],
+    position: usize
+}
+
+impl Parser<'_>
+{
+    fn peek(&self) -> Option<&str>
+    {
+        self.tokens.get(self.position).map(|(kind, _)| *kind)
+    }
+
+    fn token(&mut self, symbol: usize) -> Result<Slot, ParseError>
+    {
+        if self.peek() != Some(SYMBOLS[symbol])
+        {
+            return Err(unexpected(self.tokens, self.position));
+        }
+        self.position += 1;
+        Ok(leaf(self.tokens, self.position - 1, symbol))
+    }
+
+    // (, Item)
+    fn nonterminal_7(&mut self) -> Result<Slot, ParseError>
+    {
+        match self.peek()
+        {
+            Some(",") => Ok(reduce(0, vec![self.token(1)?, self.nonterminal_14()?])),
+            _ => Err(unexpected(self.tokens, self.position))
+        }
+    }
+
+    // (, Item)*
+    fn nonterminal_8(&mut self) -> Result<Slot, ParseError>
+    {
+        match self.peek()
+        {
+            Some(",") => Ok(reduce(1, vec![self.nonterminal_7()?, self.nonterminal_8()?])),
+            Some("]") => Ok(reduce(2, vec![])),
+            _ => Err(unexpected(self.tokens, self.position))
+        }
+    }
+
+    // (Item (, Item)*)
+    fn nonterminal_9(&mut self) -> Result<Slot, ParseError>
+    {
+        match self.peek()
+        {
+            Some("<") | Some("[") | Some("num") => Ok(reduce(3, vec![self.nonterminal_14()?, self.nonterminal_8()?])),
+            _ => Err(unexpected(self.tokens, self.position))
+        }
+    }
+
+    // (Item (, Item)*)?
+    fn nonterminal_10(&mut self) -> Result<Slot, ParseError>
+    {
+        match self.peek()
+        {
+            Some("<") | Some("[") | Some("num") => Ok(reduce(4, vec![self.nonterminal_9()?])),
+            Some("]") => Ok(reduce(5, vec![])),
+            _ => Err(unexpected(self.tokens, self.position))
+        }
+    }
+
+    // num+
+    fn nonterminal_11(&mut self) -> Result<Slot, ParseError>
+    {
+        match self.peek()
+        {
+            Some("num") => Ok(reduce(6, vec![self.token(6)?, self.nonterminal_15()?])),
+            _ => Err(unexpected(self.tokens, self.position))
+        }
+    }
+
+    // Start
+    fn nonterminal_12(&mut self) -> Result<Slot, ParseError>
+    {
+        match self.peek()
+        {
+            Some("[") => Ok(reduce(7, vec![self.nonterminal_13()?, self.token(0)?])),
+            _ => Err(unexpected(self.tokens, self.position))
+        }
+    }
+
+    // List
+    fn nonterminal_13(&mut self) -> Result<Slot, ParseError>
+    {
+        match self.peek()
+        {
+            Some("[") => Ok(reduce(8, vec![self.token(4)?, self.nonterminal_10()?, self.token(5)?])),
+            _ => Err(unexpected(self.tokens, self.position))
+        }
+    }
+
+    // Item
+    fn nonterminal_14(&mut self) -> Result<Slot, ParseError>
+    {
+        match self.peek()
+        {
+            Some("num") => Ok(reduce(9, vec![self.token(6)?])),
+            Some("<") => Ok(reduce(10, vec![self.token(2)?, self.nonterminal_11()?, self.token(3)?])),
+            Some("[") => Ok(reduce(11, vec![self.nonterminal_13()?])),
+            _ => Err(unexpected(self.tokens, self.position))
+        }
+    }
+
+    // num*
+    fn nonterminal_15(&mut self) -> Result<Slot, ParseError>
+    {
+        match self.peek()
+        {
+            Some("num") => Ok(reduce(12, vec![self.token(6)?, self.nonterminal_15()?])),
+            Some(">") => Ok(reduce(13, vec![])),
+            _ => Err(unexpected(self.tokens, self.position))
+        }
+    }
+}
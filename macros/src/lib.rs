@@ -0,0 +1,195 @@
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use compiler_utils::codegen;
+use compiler_utils::error::{Conflict, Error};
+use compiler_utils::grammar::Grammar;
+use compiler_utils::lr_parser::Mode;
+
+// grammar! { %token NUM ; Program -> Expr '$' ; ... } expands, in place, to the parser codegen generates
+// for the grammar together with its typed AST: parse, Tree, ParseError and an enum per non-terminal,
+// so it is best put in a module of its own. The grammar is written as in a grammar file, except that
+// quoted terminals have to be Rust literals, and an optional first declaration %parser picks the table:
+// LR0, SLR, LR1, LALR (the default) or LL for LL(1) recursive descent. Grammar errors and conflicts
+// become compile errors at the tokens they are about.
+#[proc_macro]
+pub fn grammar(input: TokenStream) -> TokenStream
+{
+    let mut words = Vec::<Word>::new();
+    read_words(input, &mut words);
+
+    let (mode, words) = match parser_declaration(&words)
+    {
+        Ok(declared) => declared,
+        Err((message, span)) => return compile_error(&message, span)
+    };
+    let texts = words.iter().map(|(text, _)| text.clone()).collect::<Vec<String>>();
+
+    match expand(&texts.join(" "), mode)
+    {
+        Ok(code) => code.parse().unwrap(),
+        Err(error) => blame(&error, &texts)
+            .into_iter()
+            .map(|(message, index)| compile_error(&message, index.map_or(Span::call_site(), |index| words[index].1)))
+            .collect()
+    }
+}
+
+// a word of the grammar text and the span of the tokens it came from
+type Word = (String, Span);
+
+// the tokens as the words of a grammar file, each with the span to report errors at
+fn read_words(input: TokenStream, words: &mut Vec<Word>)
+{
+    let mut tokens = input.into_iter().peekable();
+    while let Some(token) = tokens.next()
+    {
+        match token
+        {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter()
+                {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::None => {
+                        read_words(group.stream(), words);
+                        continue;
+                    }
+                };
+                words.push((open.to_string(), group.span_open()));
+                read_words(group.stream(), words);
+                words.push((close.to_string(), group.span_close()));
+            },
+            TokenTree::Punct(punct) => {
+                let next = tokens.peek().cloned();
+                match (punct.as_char(), next)
+                {
+                    ('-', Some(TokenTree::Punct(next))) if punct.spacing() == Spacing::Joint && next.as_char() == '>' => {
+                        tokens.next();
+                        words.push((String::from("->"), punct.span()));
+                    },
+                    ('%', Some(TokenTree::Ident(name))) => {
+                        tokens.next();
+                        words.push((format!("%{}", name), punct.span()));
+                    },
+                    (c, _) => words.push((c.to_string(), punct.span()))
+                }
+            },
+            TokenTree::Ident(ident) => words.push((ident.to_string(), ident.span())),
+            TokenTree::Literal(literal) => words.push((literal.to_string(), literal.span()))
+        }
+    }
+}
+
+// the mode of a leading %parser declaration, None for LL, and the words after it
+fn parser_declaration(words: &[Word]) -> Result<(Option<Mode>, &[Word]), Word>
+{
+    if words.first().map(|(text, _)| &text[..]) != Some("%parser")
+    {
+        return Ok((Some(Mode::LALR), words));
+    }
+
+    let mode = match words.get(1).map(|(text, _)| &text[..])
+    {
+        Some("LR0") => Some(Mode::LR0),
+        Some("SLR") => Some(Mode::SLR),
+        Some("LR1") => Some(Mode::LR1),
+        Some("LALR") => Some(Mode::LALR),
+        Some("LL") => None,
+        _ => return Err((String::from("expected LR0, SLR, LR1, LALR or LL after %parser"), words.get(1).unwrap_or(&words[0]).1))
+    };
+    match words.get(2)
+    {
+        Some((text, _)) if text == ";" => Ok((mode, &words[3..])),
+        _ => Err((String::from("expected ; after the %parser declaration"), words[1].1))
+    }
+}
+
+fn expand(text: &str, mode: Option<Mode>) -> Result<String, Error>
+{
+    let grammar = text.parse::<Grammar>()?;
+    let parser = match mode
+    {
+        Some(mode) => codegen::generate_lr(grammar.clone(), mode)?,
+        None => codegen::generate_ll(grammar.clone())?
+    };
+    Ok(parser + &codegen::generate_ast(&grammar))
+}
+
+// the messages for an error and the words they are about, given the words joined by single spaces;
+// conflicts are reported at the rule of their non-terminal
+fn blame(error: &Error, words: &[String]) -> Vec<(String, Option<usize>)>
+{
+    let rule = |label: &str| (0..words.len())
+        .find(|index| words[*index] == label && words.get(index + 1).map(|text| &text[..]) == Some("->"))
+        .or_else(|| words.iter().position(|text| text == label));
+
+    match error
+    {
+        Error::Syntax { column, message, .. } => {
+            let mut start = 1;
+            let mut at = None;
+            for (index, word) in words.iter().enumerate()
+            {
+                if start <= *column
+                {
+                    at = Some(index);
+                }
+                start += word.chars().count() + 1;
+            }
+            vec![(format!("grammar syntax error: {}", message), at)]
+        },
+        Error::UndefinedNonterminal(symbol) => vec![(error.to_string(), words.iter().position(|text| *text == symbol.label))],
+        Error::Conflicts(conflicts) => conflicts
+            .iter()
+            .map(|conflict| {
+                let lhs = match conflict
+                {
+                    Conflict::Predict { lhs, .. } => Some(lhs),
+                    Conflict::ShiftReduce { rule, .. } => Some(&rule.0),
+                    Conflict::ReduceReduce { rules, .. } => Some(&(rules.0).0),
+                    Conflict::Accept { .. } => None
+                };
+                (conflict.to_string(), lhs.and_then(|lhs| rule(&lhs.label)))
+            })
+            .collect(),
+        _ => vec![(error.to_string(), None)]
+    }
+}
+
+fn compile_error(message: &str, span: Span) -> TokenStream
+{
+    let mut literal = Literal::string(message);
+    literal.set_span(span);
+    let mut arguments = Group::new(Delimiter::Parenthesis, TokenTree::Literal(literal).into());
+    arguments.set_span(span);
+    let mut bang = Punct::new('!', Spacing::Alone);
+    bang.set_span(span);
+    let mut semicolon = Punct::new(';', Spacing::Alone);
+    semicolon.set_span(span);
+
+    vec![
+        TokenTree::Ident(Ident::new("compile_error", span)),
+        TokenTree::Punct(bang),
+        TokenTree::Group(arguments),
+        TokenTree::Punct(semicolon)
+    ].into_iter().collect()
+}
+
+#[test]
+fn test_blame()
+{
+    let words = "Start -> A b ; A -> a A | a ;".split(' ').map(String::from).collect::<Vec<String>>();
+
+    let error = "Start -> A b ; A -> a A | a ;".parse::<Grammar>().and_then(compiler_utils::ll_parser::LLParser::new).err().unwrap();
+    let blamed = blame(&error, &words);
+    assert_eq!(blamed.len(), 1);
+    assert!(blamed[0].0.starts_with("Predict set conflict for non-terminal A"));
+    assert_eq!(blamed[0].1, Some(5));
+
+    let error = "Start -> A b ; A -> a A | a )".parse::<Grammar>().err().unwrap();
+    let words = "Start -> A b ; A -> a A | a )".split(' ').map(String::from).collect::<Vec<String>>();
+    assert_eq!(blame(&error, &words), vec![(String::from("grammar syntax error: expected ;, found )"), Some(11))]);
+
+    let error = "Start -> B ;".parse::<Grammar>().err().unwrap();
+    assert_eq!(blame(&error, &[String::from("Start"), String::from("->"), String::from("B"), String::from(";")])[0].1, Some(2));
+}
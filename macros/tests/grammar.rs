@@ -0,0 +1,59 @@
+use compiler_utils_macros::grammar;
+
+#[allow(dead_code)]
+mod calc
+{
+    super::grammar!
+    {
+        %token NUM ;
+        Program -> Expr '$' ;
+        Expr -> Expr '+' Term
+            | Term ;
+        Term -> Term "*" Factor
+            | Factor ;
+        Factor -> '(' Expr ')'
+            | NUM ;
+    }
+}
+
+#[allow(dead_code)]
+mod lists
+{
+    super::grammar!
+    {
+        %parser LL ;
+        Start -> List '$' ;
+        List -> '[' ( Item ( ',' Item )* )? ']' ;
+        Item -> num
+            | '<' num+ '>'
+            | List ;
+    }
+}
+
+#[test]
+fn test_lalr_grammar()
+{
+    use calc::{Expr, Factor, Program, Term};
+
+    let tree = calc::parse(&[("NUM", "1"), ("+", "+"), ("NUM", "2"), ("$", "")]).unwrap();
+    let number = |text: &str| Box::new(Term::Rhs1(Box::new(Factor::Rhs1(String::from(text)))));
+    let sum = Expr::Rhs0(Box::new(Expr::Rhs1(number("1"))), String::from("+"), number("2"));
+    assert_eq!(Program::from_tree(&tree), Program::Rhs0(Box::new(sum), String::new()));
+
+    assert_eq!(calc::parse(&[("NUM", "1"), ("(", "(")]).unwrap_err().message, "Unexpected token (.");
+}
+
+#[test]
+fn test_ll_grammar()
+{
+    use lists::{Item, List, Start};
+
+    let tree = lists::parse(&[("[", "["), ("]", "]"), ("$", "$")]).unwrap();
+    assert_eq!(Start::from_tree(&tree), Start::Rhs0(Box::new(List::Rhs0(String::from("["), None, String::from("]"))), String::from("$")));
+
+    let tree = lists::parse(&[("[", "["), ("<", "<"), ("num", "4"), (">", ">"), ("]", "]"), ("$", "$")]).unwrap();
+    let Start::Rhs0(list, _) = Start::from_tree(&tree);
+    let List::Rhs0(_, items, _) = *list;
+    let lists::Group2::Rhs0(first, rest) = *items.unwrap();
+    assert_eq!((*first, rest.len()), (Item::Rhs1(String::from("<"), vec![String::from("4")], String::from(">")), 0));
+}
@@ -0,0 +1,79 @@
+use crate::symbol::Symbol;
+use crate::grammar::{Grammar, Helper};
+use crate::token::{Span, Token};
+
+// Semantic actions the parsers run while recognizing input, building a value of type T bottom up.
+pub trait Actions<T>
+{
+    // called for every terminal consumed from the input
+    fn token(&mut self, token: &Token) -> T;
+
+    // called once every symbol of production rhs_id of lhs has a value, children in rhs order;
+    // span covers the children, or is empty where the lambda production was recognized.
+    // Repeat and Optional helpers (see Grammar::helpers) are reduced once, as rhs_id 0 with one child per element
+    fn reduce(&mut self, lhs: &Symbol, rhs_id: u32, children: Vec<T>, span: Span) -> T;
+}
+
+// a value on a parse stack; Repeat and Optional helpers collect their elements until a parent takes them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Slot<T>
+{
+    Value(T),
+    List(Vec<(T, Span)>)
+}
+
+// reduces production rhs_id of lhs over children in rhs order, returning the new slot and the span it covers
+pub(crate) fn reduce_slots<T, A: Actions<T>>(actions: &mut A, grammar: &Grammar, lhs: &Symbol, rhs_id: u32, children: Vec<(Slot<T>, Span)>, span: Span) -> (Slot<T>, Span)
+{
+    let rhs = grammar.get_rhs(lhs, rhs_id).unwrap();
+
+    match grammar.helpers.get(lhs)
+    {
+        Some(Helper::Repeat { .. }) | Some(Helper::Optional { .. }) => {
+            let mut elements = Vec::<(T, Span)>::new();
+            for (symbol, (slot, child_span)) in rhs.iter().zip(children)
+            {
+                match slot
+                {
+                    // the recursion of X* or X+, left or right, continues the same list
+                    Slot::List(mut list) if same_repetition(grammar, lhs, symbol) => elements.append(&mut list),
+                    slot => elements.push((finish(actions, symbol, slot, child_span), child_span))
+                }
+            }
+
+            let span = match (elements.first(), elements.last())
+            {
+                (Some((_, first)), Some((_, last))) => first.to(last),
+                _ => span
+            };
+            (Slot::List(elements), span)
+        },
+        _ => {
+            let values = rhs
+                .iter()
+                .zip(children)
+                .map(|(symbol, (slot, child_span))| finish(actions, symbol, slot, child_span))
+                .collect::<Vec<T>>();
+            (Slot::Value(actions.reduce(lhs, rhs_id, values, span)), span)
+        }
+    }
+}
+
+// the value of a slot once it is a child of something else
+pub(crate) fn finish<T, A: Actions<T>>(actions: &mut A, symbol: &Symbol, slot: Slot<T>, span: Span) -> T
+{
+    match slot
+    {
+        Slot::Value(value) => value,
+        Slot::List(elements) => actions.reduce(symbol, 0, elements.into_iter().map(|(value, _)| value).collect(), span)
+    }
+}
+
+fn same_repetition(grammar: &Grammar, list: &Symbol, child: &Symbol) -> bool
+{
+    match (grammar.helpers.get(list), grammar.helpers.get(child))
+    {
+        (Some(Helper::Repeat { element, .. }), Some(Helper::Repeat { element: child_element, .. })) => element == child_element,
+        _ => false
+    }
+}
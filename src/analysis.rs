@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use crate::symbol::Symbol;
+use crate::grammar::Grammar;
+
+// What Grammar::analyze found wrong with a grammar, each list sorted.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Analysis
+{
+    // nonterminals used, or named as the start symbol, without any productions
+    pub undefined: Vec<Symbol>,
+    // symbols no derivation from the start symbol reaches
+    pub unreachable: Vec<Symbol>,
+    // nonterminals that derive no string of terminals
+    pub unproductive: Vec<Symbol>,
+    // (lhs, rhs_id, rhs_id of an earlier identical production)
+    pub duplicates: Vec<(Symbol, u32, u32)>
+}
+
+impl Analysis
+{
+    pub fn is_clean(&self) -> bool
+    {
+        self.undefined.is_empty() && self.unreachable.is_empty() && self.unproductive.is_empty() && self.duplicates.is_empty()
+    }
+}
+
+impl Display for Analysis
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        let mut lines = Vec::<String>::new();
+        for symbol in &self.undefined
+        {
+            lines.push(format!("Non-terminal {} is used but has no productions.", symbol));
+        }
+        for symbol in &self.unreachable
+        {
+            lines.push(format!("Symbol {} is unreachable from the start symbol.", symbol));
+        }
+        for symbol in &self.unproductive
+        {
+            lines.push(format!("Non-terminal {} derives no string of terminals.", symbol));
+        }
+        for (lhs, rhs_id, earlier) in &self.duplicates
+        {
+            lines.push(format!("Production {} of {} repeats production {}.", rhs_id, lhs, earlier));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl Grammar
+{
+    // checks the productions as they stand, so also grammars built or edited by hand
+    pub fn analyze(&self) -> Analysis
+    {
+        let mut undefined = self.productions
+            .values()
+            .flatten()
+            .flatten()
+            .chain(std::iter::once(&self.start))
+            .filter(|symbol| !symbol.terminal && !self.productions.contains_key(symbol))
+            .cloned()
+            .collect::<HashSet<Symbol>>()
+            .into_iter()
+            .collect::<Vec<Symbol>>();
+        undefined.sort();
+
+        let reachable = self.reachable_symbols();
+        let mut unreachable = self.productions
+            .iter()
+            .flat_map(|(lhs, prod_list)| std::iter::once(lhs).chain(prod_list.iter().flatten()))
+            .filter(|symbol| !reachable.contains(symbol))
+            .cloned()
+            .collect::<HashSet<Symbol>>()
+            .into_iter()
+            .collect::<Vec<Symbol>>();
+        unreachable.sort();
+
+        let productive = self.productive_symbols();
+        let mut unproductive = self.productions
+            .keys()
+            .filter(|symbol| !productive.contains(symbol))
+            .cloned()
+            .collect::<Vec<Symbol>>();
+        unproductive.sort();
+
+        let mut duplicates = Vec::<(Symbol, u32, u32)>::new();
+        for (lhs, prod_list) in &self.productions
+        {
+            for (rhs_id, rhs) in prod_list.iter().enumerate()
+            {
+                if let Some(earlier) = prod_list[..rhs_id].iter().position(|other| other == rhs)
+                {
+                    duplicates.push((lhs.clone(), rhs_id as u32, earlier as u32));
+                }
+            }
+        }
+        duplicates.sort();
+
+        Analysis
+        {
+            undefined,
+            unreachable,
+            unproductive,
+            duplicates
+        }
+    }
+
+    // the start symbol and every symbol in the productions of a reachable nonterminal
+    pub fn reachable_symbols(&self) -> HashSet<Symbol>
+    {
+        let mut reachable = HashSet::<Symbol>::new();
+        let mut to_visit = vec![self.start.clone()];
+        while let Some(symbol) = to_visit.pop()
+        {
+            if reachable.insert(symbol.clone())
+            {
+                to_visit.extend(self.productions.get(&symbol).into_iter().flatten().flatten().cloned());
+            }
+        }
+        reachable
+    }
+
+    // the nonterminals deriving some string of terminals, by fixed point
+    pub fn productive_symbols(&self) -> HashSet<Symbol>
+    {
+        let mut productive = HashSet::<Symbol>::new();
+        let mut changed = true;
+        while changed
+        {
+            changed = false;
+            for (lhs, prod_list) in &self.productions
+            {
+                if !productive.contains(lhs) && prod_list.iter().any(|rhs| rhs.iter().all(|symbol| symbol.terminal || productive.contains(symbol)))
+                {
+                    productive.insert(lhs.clone());
+                    changed = true;
+                }
+            }
+        }
+        productive
+    }
+}
+
+#[test]
+fn test_analyze()
+{
+    let mut grammar = "Start -> A '$' | B '$' ; A -> a | a | A a ; B -> B b ; C -> c D ; D -> d ;".parse::<Grammar>().unwrap();
+    let symbol = |label: &str| Symbol::from(String::from(label));
+
+    let analysis = grammar.analyze();
+    assert_eq!(analysis.undefined, vec![]);
+    assert_eq!(analysis.unreachable, vec![symbol("C"), symbol("D"), symbol("c"), symbol("d")]);
+    assert_eq!(analysis.unproductive, vec![symbol("B")]);
+    assert_eq!(analysis.duplicates, vec![(symbol("A"), 1, 0)]);
+    assert!(!analysis.is_clean());
+    assert_eq!(analysis.to_string().lines().count(), 6);
+
+    // no longer rejected once the grammar is built, and FIRST no longer panics on it
+    grammar.productions.remove(&symbol("A"));
+    grammar.compute_sets();
+    assert_eq!(grammar.analyze().undefined, vec![symbol("A")]);
+    assert!(grammar.first_of_symbol(&grammar.start.clone()).is_empty());
+
+    assert!(Grammar::from_file("data/calc").unwrap().analyze().is_clean());
+}
@@ -0,0 +1,142 @@
+use std::collections::{HashMap, HashSet};
+use crate::symbol::Symbol;
+use crate::grammar::Grammar;
+use crate::error::Error;
+
+// Builds a Grammar in code rather than from text. Names are classified as in a grammar file,
+// terminals if declared so or lowercase, except that every lhs is a non-terminal whatever its case.
+#[derive(Debug, Clone, Default)]
+pub struct GrammarBuilder
+{
+    productions: Vec<(String, Vec<String>)>,
+    terminals: HashSet<String>,
+    nonterminals: HashSet<String>,
+    start: Option<String>,
+    display_names: HashMap<String, String>
+}
+
+impl GrammarBuilder
+{
+    pub fn new() -> GrammarBuilder
+    {
+        GrammarBuilder::default()
+    }
+
+    // productions of the same lhs keep the order they were added in
+    pub fn add_production(mut self, lhs: &str, rhs: &[&str]) -> GrammarBuilder
+    {
+        self.productions.push((lhs.to_string(), rhs.iter().map(|name| name.to_string()).collect()));
+        self
+    }
+
+    // like %token
+    pub fn terminal(mut self, name: &str) -> GrammarBuilder
+    {
+        self.nonterminals.remove(name);
+        self.terminals.insert(name.to_string());
+        self
+    }
+
+    pub fn nonterminal(mut self, name: &str) -> GrammarBuilder
+    {
+        self.terminals.remove(name);
+        self.nonterminals.insert(name.to_string());
+        self
+    }
+
+    // like %start; otherwise Start if there is one, or the first lhs
+    pub fn start(mut self, name: &str) -> GrammarBuilder
+    {
+        self.start = Some(name.to_string());
+        self
+    }
+
+    // like %name: how parse errors call the terminal name
+    pub fn display_name(mut self, name: &str, display: &str) -> GrammarBuilder
+    {
+        self.display_names.insert(name.to_string(), display.to_string());
+        self
+    }
+
+    pub fn build(self) -> Result<Grammar, Error>
+    {
+        let lhs_names = self.productions.iter().map(|(lhs, _)| lhs.clone()).collect::<HashSet<String>>();
+        let symbol = |name: &String| {
+            if self.terminals.contains(name)
+            {
+                Symbol { label: name.clone(), terminal: true }
+            }
+            else if self.nonterminals.contains(name) || lhs_names.contains(name)
+            {
+                Symbol { label: name.clone(), terminal: false }
+            }
+            else
+            {
+                Symbol::from(name.clone())
+            }
+        };
+
+        let mut rules = Vec::<(Symbol, Vec<Vec<Symbol>>)>::new();
+        for (lhs, rhs) in &self.productions
+        {
+            let lhs = symbol(lhs);
+            if lhs.terminal
+            {
+                return Err(Error::TerminalProductions(lhs));
+            }
+            let rhs = rhs.iter().map(symbol).collect::<Vec<Symbol>>();
+            match rules.iter_mut().find(|(other, _)| *other == lhs)
+            {
+                Some((_, prod_list)) => prod_list.push(rhs),
+                None => rules.push((lhs, vec![rhs]))
+            }
+        }
+
+        let mut grammar = Grammar::empty();
+        grammar.declared_tokens = self.terminals.clone();
+        grammar.display_names = self.display_names
+            .iter()
+            .map(|(name, display)| (Symbol { label: name.clone(), terminal: true }, display.clone()))
+            .collect();
+        grammar.install(rules, self.start.clone())?;
+        Ok(grammar)
+    }
+}
+
+#[test]
+fn test_builder()
+{
+    let built = GrammarBuilder::new()
+        .terminal("NUM")
+        .add_production("Program", &["Expr", "$"])
+        .add_production("Expr", &["Expr", "+", "Term"])
+        .add_production("Expr", &["Term"])
+        .add_production("Term", &["Term", "*", "Factor"])
+        .add_production("Term", &["Factor"])
+        .add_production("Factor", &["(", "Expr", ")"])
+        .add_production("Factor", &["NUM"])
+        .build()
+        .unwrap();
+    let read = Grammar::from_file("data/calc").unwrap();
+
+    assert_eq!(built.start, read.start);
+    assert_eq!(built.productions, read.productions);
+    assert_eq!(built.terminals, read.terminals);
+    assert_eq!(built.nonterminals, read.nonterminals);
+    assert_eq!(built.lambda_deriving_symbols(), read.lambda_deriving_symbols());
+    assert_eq!(built.first_sets(), read.first_sets());
+    assert_eq!(built.follow_sets(), read.follow_sets());
+
+    // a lowercase lhs is a non-terminal, and nullable
+    let grammar = GrammarBuilder::new()
+        .add_production("list", &["list", "item"])
+        .add_production("list", &[])
+        .start("list")
+        .build()
+        .unwrap();
+    assert!(grammar.lambda_deriving_symbols().contains(&Symbol { label: String::from("list"), terminal: false }));
+
+    let error = GrammarBuilder::new().terminal("S").add_production("S", &["a"]).build();
+    assert_eq!(error.unwrap_err(), Error::TerminalProductions(Symbol { label: String::from("S"), terminal: true }));
+    assert!(matches!(GrammarBuilder::new().add_production("S", &["A"]).build(), Err(Error::UndefinedNonterminal(_))));
+}
@@ -0,0 +1,564 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::symbol::Symbol;
+use crate::grammar::{Grammar, Helper};
+use crate::lr_parser::{Action, LRParser, Mode};
+use crate::ll_parser::LLParser;
+use crate::error::Error;
+use crate::writer::is_identifier;
+
+// Generates standalone Rust parsers, to be written out by a build script and include!d.
+// The module depends on nothing but std: it has a parse function over (token kind, text) pairs,
+// kinds being terminal labels, building a Tree that flattens the EBNF helpers as the parsers here do.
+
+// the parts of every generated module that do not depend on the grammar
+const RUNTIME: &str = r#"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tree
+{
+    // a non-terminal with the production it was expanded by; Repeat and Optional helpers
+    // are a single node, rhs_id 0, with one child per element
+    Node
+    {
+        symbol: &'static str,
+        rhs_id: u32,
+        children: Vec<Tree>
+    },
+    // a terminal with its text and index in the tokens
+    Leaf
+    {
+        symbol: &'static str,
+        text: String,
+        position: usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError
+{
+    // index of the offending token, the number of tokens at end of input
+    pub position: usize,
+    pub message: String
+}
+
+enum Slot
+{
+    Tree(Tree),
+    List(Vec<Tree>)
+}
+
+fn finish(symbol: usize, slot: Slot) -> Tree
+{
+    match slot
+    {
+        Slot::Tree(tree) => tree,
+        Slot::List(children) => Tree::Node { symbol: SYMBOLS[symbol], rhs_id: 0, children }
+    }
+}
+
+fn reduce(production: usize, children: Vec<Slot>) -> Slot
+{
+    let (lhs, rhs_id, rhs) = PRODUCTIONS[production];
+    match LISTS[lhs]
+    {
+        Some((repeat, element)) => {
+            let mut elements = Vec::<Tree>::new();
+            for (symbol, slot) in rhs.iter().zip(children)
+            {
+                match slot
+                {
+                    // the recursion of X* or X+ continues the same list
+                    Slot::List(mut list) if repeat && LISTS[*symbol] == Some((true, element)) => elements.append(&mut list),
+                    slot => elements.push(finish(*symbol, slot))
+                }
+            }
+            Slot::List(elements)
+        },
+        None => Slot::Tree(Tree::Node
+        {
+            symbol: SYMBOLS[lhs],
+            rhs_id,
+            children: rhs.iter().zip(children).map(|(symbol, slot)| finish(*symbol, slot)).collect()
+        })
+    }
+}
+
+fn unexpected(tokens: &[(&str, &str)], position: usize) -> ParseError
+{
+    let message = match tokens.get(position)
+    {
+        Some((kind, _)) => format!("Unexpected token {}.", kind),
+        None => String::from("Unexpected end of file.")
+    };
+    ParseError { position, message }
+}
+
+fn leaf(tokens: &[(&str, &str)], position: usize, symbol: usize) -> Slot
+{
+    Slot::Tree(Tree::Leaf { symbol: SYMBOLS[symbol], text: tokens[position].1.to_string(), position })
+}
+"#;
+
+const LR_DRIVER: &str = r#"
+pub fn parse(tokens: &[(&str, &str)]) -> Result<Tree, ParseError>
+{
+    // (state, value) pairs
+    let mut stack = Vec::<(usize, Slot)>::new();
+    let mut position = 0;
+    // the non-terminal produced by the last reduction, consumed before any further input
+    let mut reduced: Option<(usize, Slot)> = None;
+
+    loop
+    {
+        let state = stack.last().map_or(0, |(state, _)| *state);
+        let column = match (&reduced, tokens.get(position))
+        {
+            (Some((symbol, _)), _) => symbol + 1,
+            (None, Some((kind, _))) => terminal(kind).ok_or_else(|| unexpected(tokens, position))? + 1,
+            (None, None) => 0
+        };
+
+        match ACTION[state][column]
+        {
+            0 => return Err(unexpected(tokens, position)),
+            ACCEPT => {
+                if position < tokens.len()
+                {
+                    return Err(ParseError { position, message: format!("Unexpected token {} after the end of the parse.", tokens[position].0) });
+                }
+                let (symbol, slot) = reduced.take().unwrap();
+                return Ok(finish(symbol, slot));
+            },
+            action if action > 0 => {
+                let slot = match reduced.take()
+                {
+                    Some((_, slot)) => slot,
+                    None => {
+                        position += 1;
+                        leaf(tokens, position - 1, column - 1)
+                    }
+                };
+                stack.push((action as usize - 1, slot));
+            },
+            action => {
+                let production = (-action - 1) as usize;
+                let (lhs, _, rhs) = PRODUCTIONS[production];
+                let children = stack.split_off(stack.len() - rhs.len()).into_iter().map(|(_, slot)| slot).collect();
+                reduced = Some((lhs, reduce(production, children)));
+            }
+        }
+    }
+}
+"#;
+
+const LL_DRIVER: &str = r#"
+pub fn parse(tokens: &[(&str, &str)]) -> Result<Tree, ParseError>
+{
+    let mut parser = Parser { tokens, position: 0 };
+    let slot = parser.START()?;
+    if parser.position < tokens.len()
+    {
+        return Err(ParseError { position: parser.position, message: format!("Unexpected token {} after the end of the parse.", tokens[parser.position].0) });
+    }
+    Ok(finish(START, slot))
+}
+
+struct Parser<'a>
+{
+    tokens: &'a [(&'a str, &'a str)],
+    position: usize
+}
+
+impl Parser<'_>
+{
+    fn peek(&self) -> Option<&str>
+    {
+        self.tokens.get(self.position).map(|(kind, _)| *kind)
+    }
+
+    fn token(&mut self, symbol: usize) -> Result<Slot, ParseError>
+    {
+        if self.peek() != Some(SYMBOLS[symbol])
+        {
+            return Err(unexpected(self.tokens, self.position));
+        }
+        self.position += 1;
+        Ok(leaf(self.tokens, self.position - 1, symbol))
+    }
+"#;
+
+// symbol ids: terminals in label order, then the non-terminals in rule order
+fn symbol_ids(grammar: &Grammar) -> (Vec<Symbol>, HashMap<Symbol, usize>)
+{
+    let mut symbols = grammar.terminals.iter().cloned().collect::<Vec<Symbol>>();
+    symbols.sort();
+    symbols.extend(grammar.rule_order());
+    let ids = symbols.iter().enumerate().map(|(id, symbol)| (symbol.clone(), id)).collect();
+    (symbols, ids)
+}
+
+// the tables shared by both kinds of parser
+fn common_items(grammar: &Grammar, description: &str, symbols: &[Symbol], ids: &HashMap<Symbol, usize>, productions: &[(Symbol, u32, Vec<Symbol>)]) -> String
+{
+    let mut out = format!("// Generated by compiler_utils, {}, from grammar {:016x}; do not edit.\n\n", description, grammar.fingerprint());
+
+    out.push_str("pub static SYMBOLS: &[&str] = &[\n");
+    for symbol in symbols
+    {
+        out.push_str(&format!("    {:?},\n", symbol.label));
+    }
+    out.push_str("];\n\n");
+
+    out.push_str("// (lhs, rhs_id, rhs) by production index\nstatic PRODUCTIONS: &[(usize, u32, &[usize])] = &[\n");
+    for (lhs, rhs_id, rhs) in productions
+    {
+        let rule = std::iter::once(format!("{} ->", lhs)).chain(rhs.iter().map(|symbol| symbol.label.clone())).collect::<Vec<String>>().join(" ");
+        let rhs = rhs.iter().map(|symbol| ids[symbol].to_string()).collect::<Vec<String>>().join(", ");
+        out.push_str(&format!("    ({}, {}, &[{}]), // {}\n", ids[lhs], rhs_id, rhs, rule));
+    }
+    out.push_str("];\n\n");
+
+    out.push_str("// Some((repeat, element)) for the Repeat and Optional helpers, by symbol\nstatic LISTS: &[Option<(bool, usize)>] = &[\n");
+    for symbol in symbols
+    {
+        match grammar.helpers.get(symbol)
+        {
+            Some(Helper::Repeat { element, .. }) => out.push_str(&format!("    Some((true, {})),\n", ids[element])),
+            Some(Helper::Optional { element }) => out.push_str(&format!("    Some((false, {})),\n", ids[element])),
+            _ => out.push_str("    None,\n")
+        }
+    }
+    out.push_str("];\n");
+    out.push_str(RUNTIME);
+    out
+}
+
+// a table-driven module for the mode's LR table of grammar
+pub fn generate_lr(grammar: Grammar, mode: Mode) -> Result<String, Error>
+{
+    // never traced, as this runs from build scripts and the grammar! macro
+    let parser = LRParser::new(grammar, mode)?;
+    let grammar = &parser.grammar;
+    let (symbols, ids) = symbol_ids(grammar);
+    let productions = grammar.production_list();
+
+    let mut out = common_items(grammar, &format!("{:?} table", mode), &symbols, &ids, &productions);
+
+    out.push_str("\nfn terminal(kind: &str) -> Option<usize>\n{\n    match kind\n    {\n");
+    for symbol in symbols.iter().filter(|symbol| symbol.terminal)
+    {
+        out.push_str(&format!("        {:?} => Some({}),\n", symbol.label, ids[symbol]));
+    }
+    out.push_str("        _ => None\n    }\n}\n");
+
+    let states = parser.parse_table.keys().map(|(state, _)| *state as usize + 1).max().unwrap_or(0);
+    let mut rows = vec![vec![0i64; symbols.len() + 1]; states];
+    for ((state, symbol), action) in &parser.parse_table
+    {
+        let column = symbol.as_ref().map_or(0, |symbol| ids[symbol] + 1);
+        rows[*state as usize][column] = match action
+        {
+            Action::Shift(next) => *next as i64 + 1,
+            Action::Reduce((lhs, rhs_id)) => -(productions.iter().position(|(other, other_id, _)| other == lhs && other_id == rhs_id).unwrap() as i64) - 1,
+            Action::Accept => i32::MAX as i64
+        };
+    }
+    out.push_str("\nconst ACCEPT: i32 = i32::MAX;\n\n");
+    out.push_str("// by state, then 0 for end of input or symbol + 1: 0 is an error, n > 0 shifts to state n - 1,\n");
+    out.push_str("// n < 0 reduces production -n - 1\nstatic ACTION: &[&[i32]] = &[\n");
+    for row in rows
+    {
+        let row = row.iter().map(|action| match *action == i32::MAX as i64
+        {
+            true => String::from("ACCEPT"),
+            false => action.to_string()
+        });
+        out.push_str(&format!("    &[{}],\n", row.collect::<Vec<String>>().join(", ")));
+    }
+    out.push_str("];\n");
+    out.push_str(LR_DRIVER);
+    Ok(out)
+}
+
+// a recursive descent module for an LL(1) grammar, one function per non-terminal choosing
+// its production by LLParser's predict table
+pub fn generate_ll(grammar: Grammar) -> Result<String, Error>
+{
+    let parser = LLParser::new(grammar)?;
+    let grammar = &parser.grammar;
+    let (symbols, ids) = symbol_ids(grammar);
+    let productions = grammar.production_list();
+
+    let mut out = common_items(grammar, "LL(1) recursive descent", &symbols, &ids, &productions);
+    out.push_str(&format!("\nconst START: usize = {};\n", ids[&grammar.start]));
+    out.push_str(&LL_DRIVER.replace("parser.START()", &format!("parser.nonterminal_{}()", ids[&grammar.start])));
+
+    for lhs in grammar.rule_order()
+    {
+        // the lookahead patterns of every production, in rhs_id order
+        let mut predicted = BTreeMap::<u32, Vec<String>>::new();
+        for ((other, lookahead), rhs_id) in &parser.parse_table
+        {
+            if *other == lhs
+            {
+                predicted.entry(*rhs_id).or_default().push(match lookahead.first()
+                {
+                    Some(symbol) => format!("Some({:?})", symbol.label),
+                    None => String::from("None")
+                });
+            }
+        }
+
+        out.push_str(&format!("\n    // {}\n    fn nonterminal_{}(&mut self) -> Result<Slot, ParseError>\n    {{\n        match self.peek()\n        {{\n", lhs, ids[&lhs]));
+        for (rhs_id, mut patterns) in predicted
+        {
+            patterns.sort();
+            let production = productions.iter().position(|(other, other_id, _)| *other == lhs && *other_id == rhs_id).unwrap();
+            let children = productions[production].2
+                .iter()
+                .map(|symbol| match symbol.terminal
+                {
+                    true => format!("self.token({})?", ids[symbol]),
+                    false => format!("self.nonterminal_{}()?", ids[symbol])
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            out.push_str(&format!("            {} => Ok(reduce({}, vec![{}])),\n", patterns.join(" | "), production, children));
+        }
+        out.push_str("            _ => Err(unexpected(self.tokens, self.position))\n        }\n    }\n");
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+// names the generated modules use themselves, or that would shadow the std types the AST uses
+const RESERVED: &[&str] = &["Tree", "ParseError", "Slot", "Parser", "Box", "Vec", "Option", "String", "Self"];
+
+// the Rust type of every non-terminal but the Repeat and Optional helpers: its label where that is an
+// identifier, Group1, Group2 ... for parenthesized groups, otherwise the label made into one
+fn ast_names(grammar: &Grammar) -> HashMap<Symbol, String>
+{
+    let mut names = HashMap::<Symbol, String>::new();
+    let mut used = RESERVED.iter().map(|name| name.to_string()).collect::<Vec<String>>();
+    let mut groups = 0;
+    for symbol in grammar.rule_order()
+    {
+        let base = match grammar.helpers.get(&symbol)
+        {
+            Some(Helper::Group) => {
+                groups += 1;
+                format!("Group{}", groups)
+            },
+            Some(_) => continue,
+            None if is_identifier(&symbol.label) => symbol.label.clone(),
+            None => symbol.label
+                .replace('\'', "Prime")
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect::<String>()
+        };
+        let base = match base.chars().next()
+        {
+            Some(c) if c.is_ascii_alphabetic() => base,
+            _ => format!("N{}", base)
+        };
+
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while used.contains(&name)
+        {
+            suffix += 1;
+            name = format!("{}{}", base, suffix);
+        }
+        used.push(name.clone());
+        names.insert(symbol, name);
+    }
+    names
+}
+
+// the field type for a child of symbol; Vec already boxes its elements
+fn ast_type(grammar: &Grammar, names: &HashMap<Symbol, String>, symbol: &Symbol, boxed: bool) -> String
+{
+    match grammar.helpers.get(symbol)
+    {
+        _ if symbol.terminal => String::from("String"),
+        Some(Helper::Repeat { element, .. }) => format!("Vec<{}>", ast_type(grammar, names, element, false)),
+        Some(Helper::Optional { element }) => format!("Option<{}>", ast_type(grammar, names, element, true)),
+        _ if boxed => format!("Box<{}>", names[symbol]),
+        _ => names[symbol].clone()
+    }
+}
+
+// an expression converting tree, a &Tree of symbol, to its ast_type
+fn ast_conversion(grammar: &Grammar, names: &HashMap<Symbol, String>, symbol: &Symbol, tree: &str, boxed: bool) -> String
+{
+    match grammar.helpers.get(symbol)
+    {
+        _ if symbol.terminal => format!("text({})", tree),
+        Some(Helper::Repeat { element, .. }) => {
+            let element = match grammar.helpers.get(element)
+            {
+                _ if element.terminal => String::from("text"),
+                Some(Helper::Repeat { .. }) | Some(Helper::Optional { .. }) => format!("|tree| {}", ast_conversion(grammar, names, element, "tree", false)),
+                _ => format!("{}::from_tree", names[element])
+            };
+            format!("children({}).iter().map({}).collect()", tree, element)
+        },
+        Some(Helper::Optional { element }) => format!("children({}).first().map(|tree| {})", tree, ast_conversion(grammar, names, element, "tree", true)),
+        _ if boxed => format!("Box::new({}::from_tree({}))", names[symbol], tree),
+        _ => format!("{}::from_tree({})", names[symbol], tree)
+    }
+}
+
+// Typed AST enums over the Tree of a module from generate_lr or generate_ll for the same grammar:
+// one enum per non-terminal and parenthesized group, with a variant Rhs0, Rhs1 ... per production
+// holding its children, terminals as their text, X* and X+ as a Vec and X? as an Option.
+// Each has a from_tree for the trees parse returns.
+pub fn generate_ast(grammar: &Grammar) -> String
+{
+    let names = ast_names(grammar);
+    let mut out = String::from("
+fn text(tree: &Tree) -> String
+{
+    match tree
+    {
+        Tree::Leaf { text, .. } => text.clone(),
+        Tree::Node { .. } => panic!(\"a terminal was expected\")
+    }
+}
+
+fn children(tree: &Tree) -> &[Tree]
+{
+    match tree
+    {
+        Tree::Node { children, .. } => children,
+        Tree::Leaf { .. } => &[]
+    }
+}
+
+fn rhs_id(tree: &Tree) -> u32
+{
+    match tree
+    {
+        Tree::Node { rhs_id, .. } => *rhs_id,
+        Tree::Leaf { .. } => 0
+    }
+}
+");
+
+    for lhs in grammar.rule_order()
+    {
+        let name = match names.get(&lhs)
+        {
+            Some(name) => name,
+            None => continue
+        };
+        let mut variants = Vec::<String>::new();
+        let mut arms = Vec::<String>::new();
+        let mut constructions = Vec::<String>::new();
+        for (rhs_id, rhs) in grammar.productions[&lhs].iter().enumerate()
+        {
+            let rule = std::iter::once(format!("{} ->", lhs)).chain(rhs.iter().map(|symbol| symbol.label.clone())).collect::<Vec<String>>().join(" ");
+            let (variant, construction) = match rhs.is_empty()
+            {
+                true => (format!("Rhs{}", rhs_id), format!("{}::Rhs{}", name, rhs_id)),
+                false => (
+                    format!("Rhs{}({})", rhs_id, rhs.iter().map(|symbol| ast_type(grammar, &names, symbol, true)).collect::<Vec<String>>().join(", ")),
+                    format!("{}::Rhs{}({})", name, rhs_id, rhs
+                        .iter()
+                        .enumerate()
+                        .map(|(index, symbol)| ast_conversion(grammar, &names, symbol, &format!("&children(tree)[{}]", index), true))
+                        .collect::<Vec<String>>()
+                        .join(", ")))
+            };
+            variants.push(format!("    // {}\n    {},\n", rule, variant));
+            match rhs_id + 1 == grammar.productions[&lhs].len()
+            {
+                true => arms.push(format!("            _ => {}\n", construction)),
+                false => arms.push(format!("            {} => {},\n", rhs_id, construction))
+            }
+            constructions.push(construction);
+        }
+
+        out.push_str(&format!("\n#[derive(Debug, Clone, PartialEq, Eq)]\n#[allow(clippy::enum_variant_names)]\npub enum {}\n{{\n{}}}\n", name, variants.concat()));
+        let body = match arms.len()
+        {
+            1 => format!("        {}\n", constructions[0]),
+            _ => format!("        match rhs_id(tree)\n        {{\n{}        }}\n", arms.concat())
+        };
+        out.push_str(&format!("\nimpl {}\n{{\n    pub fn from_tree(tree: &Tree) -> {}\n    {{\n{}    }}\n}}\n", name, name, body));
+    }
+    out
+}
+
+#[cfg(test)]
+#[allow(dead_code)]
+mod calc_lalr
+{
+    include!("../data/calc_lalr.rs");
+}
+
+#[cfg(test)]
+#[allow(dead_code)]
+mod ebnf_ll
+{
+    include!("../data/ebnf_ll.rs");
+    include!("../data/ebnf_ast.rs");
+}
+
+#[test]
+fn test_generate_lr()
+{
+    use calc_lalr::{parse, Tree};
+
+    // data/calc_lalr.rs was generated by this
+    let generated = generate_lr(Grammar::from_file("data/calc").unwrap(), Mode::LALR).unwrap();
+    assert_eq!(generated, std::fs::read_to_string("data/calc_lalr.rs").unwrap());
+
+    let tree = parse(&[("NUM", "1"), ("+", "+"), ("NUM", "2"), ("*", "*"), ("NUM", "3"), ("$", "")]).unwrap();
+    let Tree::Node { symbol, children, .. } = tree else { panic!("a leaf at the root") };
+    assert_eq!((symbol, children.len()), ("Program", 2));
+    assert!(matches!(&children[0], Tree::Node { symbol: "Expr", rhs_id: 0, children } if children.len() == 3));
+
+    let error = parse(&[("NUM", "1"), ("NUM", "2")]).unwrap_err();
+    assert_eq!((error.position, &error.message[..]), (1, "Unexpected token NUM."));
+    assert_eq!(parse(&[("NUM", "1")]).unwrap_err().message, "Unexpected end of file.");
+}
+
+#[test]
+fn test_generate_ll()
+{
+    use ebnf_ll::{parse, Tree};
+
+    let generated = generate_ll(Grammar::from_file("data/ebnf").unwrap()).unwrap();
+    assert_eq!(generated, std::fs::read_to_string("data/ebnf_ll.rs").unwrap());
+
+    // the same tree the LL and LR parsers build, less the spans
+    let tokens = [("[", "["), ("num", "1"), (",", ","), ("<", "<"), ("num", "2"), ("num", "3"), (">", ">"), (",", ","), ("[", "["), ("]", "]"), ("]", "]"), ("$", "$")];
+    let tree = parse(&tokens).unwrap();
+    fn shape(tree: &Tree) -> String
+    {
+        match tree
+        {
+            Tree::Node { symbol, children, .. } => format!("{}({})", symbol, children.iter().map(shape).collect::<Vec<String>>().join(" ")),
+            Tree::Leaf { text, .. } => text.clone()
+        }
+    }
+    assert_eq!(shape(&tree), "Start(List([ (Item (, Item)*)?((Item (, Item)*)(Item(1) (, Item)*((, Item)(, Item(< num+(2 3) >)) (, Item)(, Item(List([ (Item (, Item)*)?() ])))))) ]) $)");
+    assert_eq!(parse(&tokens[..3]).unwrap_err().message, "Unexpected end of file.");
+
+    assert!(matches!(generate_ll(Grammar::from_file("data/ll3").unwrap()), Err(Error::Conflicts(_))));
+}
+
+#[test]
+fn test_generate_ast()
+{
+    use ebnf_ll::{parse, Group1, Group2, Item, List, Start};
+
+    assert_eq!(generate_ast(&Grammar::from_file("data/ebnf").unwrap()), std::fs::read_to_string("data/ebnf_ast.rs").unwrap());
+
+    let tokens = [("[", "["), ("num", "1"), (",", ","), ("<", "<"), ("num", "2"), ("num", "3"), (">", ">"), ("]", "]"), ("$", "$")];
+    let ast = Start::from_tree(&parse(&tokens).unwrap());
+    let two_three = Item::Rhs1(String::from("<"), vec![String::from("2"), String::from("3")], String::from(">"));
+    let items = Group2::Rhs0(Box::new(Item::Rhs0(String::from("1"))), vec![Group1::Rhs0(String::from(","), Box::new(two_three))]);
+    assert_eq!(ast, Start::Rhs0(Box::new(List::Rhs0(String::from("["), Some(Box::new(items)), String::from("]"))), String::from("$")));
+}
@@ -0,0 +1,241 @@
+use std::fmt::{Display, Formatter};
+use crate::symbol::Symbol;
+use crate::grammar::Grammar;
+use crate::token::Span;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error
+{
+    Io
+    {
+        filename: String,
+        message: String
+    },
+    // malformed grammar text, positions are 1-based
+    Syntax
+    {
+        line: usize,
+        column: usize,
+        message: String
+    },
+    UndefinedNonterminal(Symbol),
+    // a built grammar gave productions to a name declared a terminal
+    TerminalProductions(Symbol),
+    // derives itself (A =>+ A), so its left recursion cannot be removed
+    Cycle(Symbol),
+    // an LL(k) parser asked for with k = 0
+    Lookahead(usize),
+    Conflicts(Vec<Conflict>),
+    // malformed token pattern, position counts characters of the pattern
+    Regex
+    {
+        rule: String,
+        position: usize,
+        message: String
+    },
+    // input no token rule matches
+    Lex
+    {
+        span: Span,
+        message: String
+    },
+    // saved parse tables that are malformed, or of another version or grammar
+    Tables(String),
+    // input the grammar does not derive: found where only the expected terminals could go on,
+    // None standing for the end of input in both
+    Parse
+    {
+        span: Span,
+        found: Option<Symbol>,
+        expected: Vec<Option<Symbol>>,
+        message: String
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict
+{
+    // two productions of lhs are both predicted by lookahead, up to k symbols, fewer where the input ends;
+    // resolved_at is the smallest k up to LLParser::MAX_LOOKAHEAD that tells them apart, if any
+    Predict
+    {
+        lhs: Symbol,
+        lookahead: Vec<Symbol>,
+        productions: (u32, u32),
+        resolved_at: Option<usize>
+    },
+    // items are the conflicting dotted rules of the state; prefix is a shortest sentential form that
+    // reaches the state, and input the same with every non-terminal replaced by a shortest string it
+    // derives, so input followed by symbol is an input on which the parser cannot decide
+    ShiftReduce
+    {
+        state: u32,
+        symbol: Option<Symbol>,
+        rule: (Symbol, u32),
+        items: Vec<String>,
+        prefix: Vec<Symbol>,
+        input: Vec<Symbol>
+    },
+    ReduceReduce
+    {
+        state: u32,
+        symbol: Option<Symbol>,
+        rules: ((Symbol, u32), (Symbol, u32)),
+        items: Vec<String>,
+        prefix: Vec<Symbol>,
+        input: Vec<Symbol>,
+        // only known for LALR tables: whether canonical LR(1) would have split the state
+        lalr_specific: Option<bool>
+    },
+    Accept
+    {
+        state: u32,
+        symbol: Option<Symbol>
+    }
+}
+
+impl Conflict
+{
+    // whether both conflicts are between the same two reductions on the same symbol, in any state
+    pub fn same_reductions(&self, other: &Conflict) -> bool
+    {
+        match (self, other)
+        {
+            (Conflict::ReduceReduce { symbol, rules: (a, b), .. }, Conflict::ReduceReduce { symbol: other_symbol, rules: (c, d), .. }) => {
+                symbol == other_symbol && ((a == c && b == d) || (a == d && b == c))
+            },
+            _ => false
+        }
+    }
+}
+
+// the result of a parse with error recovery: the value, unless the errors could not be recovered from,
+// and every error reported on the way, in input order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovered<T>
+{
+    pub value: Option<T>,
+    pub errors: Vec<Error>
+}
+
+struct Lookahead<'a>(&'a Option<Symbol>);
+
+impl Display for Lookahead<'_>
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        match self.0
+        {
+            Some(symbol) => write!(f, "{}", symbol),
+            None => write!(f, "end of input")
+        }
+    }
+}
+
+impl Display for Conflict
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        let conflict = match self
+        {
+            Conflict::Predict { lhs, lookahead, productions, resolved_at } => {
+                let lookahead = match lookahead.is_empty()
+                {
+                    true => String::from("end of input"),
+                    false => lookahead.iter().map(|symbol| symbol.label.clone()).collect::<Vec<String>>().join(" ")
+                };
+                write!(f, "Predict set conflict for non-terminal {} with lookahead {} between productions {} and {}.", lhs, lookahead, productions.0, productions.1)?;
+                return match resolved_at
+                {
+                    Some(k) => write!(f, " LL({}) lookahead resolves it.", k),
+                    None => write!(f, " No LL(k) lookahead resolves it.")
+                };
+            },
+            Conflict::ShiftReduce { state, symbol, rule, items, prefix, input } => {
+                write!(f, "Shift-reduce conflict at state {} with symbol {} (reduce {} {}).", state, Lookahead(symbol), rule.0, rule.1)?;
+                (items, symbol, prefix, input)
+            },
+            Conflict::ReduceReduce { state, symbol, rules, items, prefix, input, lalr_specific } => {
+                write!(f, "Reduce-reduce conflict at state {} with symbol {} (reduce {} {} or {} {}).", state, Lookahead(symbol), (rules.0).0, (rules.0).1, (rules.1).0, (rules.1).1)?;
+                match lalr_specific
+                {
+                    Some(true) => write!(f, " LALR-specific (introduced by merging LR(1) states).")?,
+                    Some(false) => write!(f, " Inherent to the grammar (also a canonical LR(1) conflict).")?,
+                    None => {}
+                }
+                (items, symbol, prefix, input)
+            },
+            Conflict::Accept { state, symbol } => {
+                return write!(f, "Accept conflict at state {} with symbol {}.", state, Lookahead(symbol));
+            }
+        };
+
+        let (items, symbol, prefix, input) = conflict;
+        for item in items
+        {
+            write!(f, "\n    {}", item)?;
+        }
+        // ~ marks where the parser has to choose
+        let sentence = |symbols: &Vec<Symbol>| symbols.iter().map(|symbol| format!("{} ", symbol)).collect::<String>();
+        write!(f, "\n  Example: {}~ {}", sentence(prefix), Lookahead(symbol))?;
+        write!(f, "\n  Input:   {}~ {}", sentence(input), Lookahead(symbol))
+    }
+}
+
+impl Error
+{
+    // the parse error for found, with the expected terminals listed end of input last and
+    // named by their display names in the grammar
+    pub(crate) fn unexpected(grammar: &Grammar, span: Span, found: Option<Symbol>, mut expected: Vec<Option<Symbol>>) -> Error
+    {
+        expected.sort_by_key(|symbol| (symbol.is_none(), symbol.clone()));
+        expected.dedup();
+
+        let name = |symbol: &Option<Symbol>| match symbol
+        {
+            Some(symbol) => grammar.display_name(symbol),
+            None => String::from("end of input")
+        };
+        let names = expected.iter().map(name).collect::<Vec<String>>();
+        let message = match names.len()
+        {
+            0 => format!("Unexpected {}.", name(&found)),
+            1 => format!("Expected {}; found {}.", names[0], name(&found)),
+            _ => format!("Expected one of {}; found {}.", names.join(", "), name(&found))
+        };
+        Error::Parse { span, found, expected, message }
+    }
+}
+
+impl Display for Error
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            Error::Io { filename, message } => write!(f, "Could not read {}: {}", filename, message),
+            Error::Syntax { line, column, message } => write!(f, "Syntax error at {}:{}: {}", line, column, message),
+            Error::UndefinedNonterminal(symbol) => write!(f, "Non-terminal {} is used but has no productions.", symbol),
+            Error::TerminalProductions(symbol) => write!(f, "Terminal {} cannot have productions.", symbol),
+            Error::Lookahead(k) => write!(f, "LL(k) needs at least one token of lookahead, not {}.", k),
+            Error::Cycle(symbol) => write!(f, "Non-terminal {} derives itself, so its left recursion cannot be removed.", symbol),
+            Error::Regex { rule, position, message } => write!(f, "Bad pattern for token {} at {}: {}", rule, position, message),
+            Error::Lex { span, message } => write!(f, "Lexical error at {}:{}: {}", span.line, span.column, message),
+            Error::Tables(message) => write!(f, "Could not load parse tables: {}", message),
+            Error::Parse { span, message, .. } => write!(f, "Parse error at {}:{}: {}", span.line, span.column, message),
+            Error::Conflicts(conflicts) => {
+                for (index, conflict) in conflicts.iter().enumerate()
+                {
+                    if index > 0
+                    {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", conflict)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
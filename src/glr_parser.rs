@@ -0,0 +1,513 @@
+use std::collections::HashMap;
+use crate::symbol::Symbol;
+use crate::grammar::Grammar;
+use crate::lr_parser::{Action, LRParser, Mode};
+use crate::parse_tree::{ParseTree, TreeBuilder};
+use crate::actions::{self, Actions, Slot};
+use crate::error::{Conflict, Error};
+use crate::token::{Span, Token};
+
+// A shared packed parse forest: every derivation of the input, with the parts they have in common shared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Forest
+{
+    pub nodes: Vec<ForestNode>,
+    pub root: usize
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForestNode
+{
+    // a token of the input
+    Leaf(Token),
+    // symbol derived from the tokens start up to end, in as many ways as it has alternatives
+    Symbol
+    {
+        symbol: Symbol,
+        start: usize,
+        end: usize,
+        span: Span,
+        alternatives: Vec<Packed>
+    }
+}
+
+// one derivation of a forest node: production rhs_id of its symbol, over the child nodes in rhs order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packed
+{
+    pub rhs_id: u32,
+    pub children: Vec<usize>
+}
+
+impl Forest
+{
+    // the nodes reachable from the root that have more than one derivation, outermost first
+    pub fn ambiguities(&self) -> Vec<usize>
+    {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut work_list = vec![self.root];
+        let mut ambiguities = Vec::<usize>::new();
+        while let Some(node) = work_list.pop()
+        {
+            if seen[node]
+            {
+                continue;
+            }
+            seen[node] = true;
+
+            if let ForestNode::Symbol { alternatives, .. } = &self.nodes[node]
+            {
+                if alternatives.len() > 1
+                {
+                    ambiguities.push(node);
+                }
+                for alternative in alternatives.iter()
+                {
+                    work_list.extend(alternative.children.iter().rev());
+                }
+            }
+        }
+        ambiguities
+    }
+
+    pub fn is_ambiguous(&self) -> bool
+    {
+        !self.ambiguities().is_empty()
+    }
+}
+
+// Hooks that settle the ambiguities of a forest while it is evaluated.
+pub trait Disambiguator
+{
+    // whether alternative may derive node at all, e.g. not where precedence forbids the nesting
+    fn allow(&mut self, _forest: &Forest, _node: usize, _alternative: &Packed) -> bool
+    {
+        true
+    }
+
+    // picks one of the allowed alternatives of node, of which there are at least two, by its index
+    // in alternatives; an index past the end fails the evaluation
+    fn choose(&mut self, _forest: &Forest, _node: usize, _alternatives: &[&Packed]) -> usize
+    {
+        0
+    }
+}
+
+// allows everything and takes the derivation the parser found first
+pub struct FirstAlternative;
+
+impl Disambiguator for FirstAlternative {}
+
+// a node of the graph-structured stack: the parsers in state after reading level tokens,
+// which share the stacks below it
+struct StackNode
+{
+    state: u32,
+    level: usize,
+    // the node below and the forest node of the symbol in between
+    edges: Vec<(usize, usize)>
+}
+
+pub struct GLRParser
+{
+    pub(crate) grammar: Grammar,
+    // the actions of the LR table, each followed by the reductions it conflicted with
+    pub(crate) parse_table: HashMap<(u32, Option<Symbol>), Vec<Action>>
+}
+
+impl GLRParser
+{
+    // builds the LR table of mode; conflicts that precedence does not settle are kept as alternatives
+    pub fn new(grammar: Grammar, mode: Mode) -> Result<GLRParser, Error>
+    {
+        let mut lr_parser = LRParser::unbuilt(grammar, mode, false);
+        let conflicts = lr_parser.fill_table();
+
+        let mut parse_table = lr_parser.parse_table
+            .into_iter()
+            .map(|(key, action)| (key, vec![action]))
+            .collect::<HashMap<(u32, Option<Symbol>), Vec<Action>>>();
+        for conflict in conflicts
+        {
+            let (key, rule) = match conflict
+            {
+                Conflict::ShiftReduce { state, symbol, rule, .. } => ((state, symbol), rule),
+                Conflict::ReduceReduce { state, symbol, rules, .. } => ((state, symbol), rules.1),
+                Conflict::Accept { .. } | Conflict::Predict { .. } => continue
+            };
+            // Entries of non-terminals are gotos here. Only LR(0) also reduces on them, and it enters the same
+            // reduction for every terminal and the end of input, where it is kept; nothing is lost. That covers
+            // Accept too, which is only ever the start symbol's entry in state 0, on which LR(0) does not reduce.
+            if key.1.as_ref().is_some_and(|symbol| !symbol.terminal)
+            {
+                continue;
+            }
+
+            let actions = parse_table.entry(key).or_default();
+            let action = Action::Reduce(rule);
+            if !actions.contains(&action)
+            {
+                actions.push(action);
+            }
+        }
+
+        Ok(GLRParser{
+            grammar: lr_parser.grammar,
+            parse_table
+        })
+    }
+
+    // parses whitespace separated symbols, taking the first derivation where there are several
+    pub fn parse(&self, program: String) -> Result<ParseTree, Error>
+    {
+        self.parse_tokens(Token::split_whitespace(&program))
+    }
+
+    pub fn parse_tokens(&self, tokens: Vec<Token>) -> Result<ParseTree, Error>
+    {
+        self.parse_with(tokens, &mut TreeBuilder, &mut FirstAlternative)
+    }
+
+    pub fn parse_with<T, A: Actions<T>, D: Disambiguator>(&self, tokens: Vec<Token>, actions: &mut A, disambiguator: &mut D) -> Result<T, Error>
+    {
+        let forest = self.parse_forest(tokens)?;
+        self.evaluate(&forest, actions, disambiguator)
+    }
+
+    // every derivation of the input. All parsers move on a token together; before each shift the
+    // reductions are redone until they add nothing, since a reduction may open paths for others
+    pub fn parse_forest(&self, tokens: Vec<Token>) -> Result<Forest, Error>
+    {
+        let end_span = tokens.last().map_or(Span::origin(), |token| token.end());
+
+        let mut nodes = Vec::<ForestNode>::new();
+        // forest nodes by symbol, start and end
+        let mut derivations = HashMap::<(Symbol, usize, usize), usize>::new();
+        let mut stack = vec![StackNode { state: 0, level: 0, edges: vec![] }];
+        // stack nodes by state and level
+        let mut stack_nodes = HashMap::<(u32, usize), usize>::new();
+        stack_nodes.insert((0, 0), 0);
+        let mut frontier = vec![0];
+        let mut root: Option<usize> = None;
+
+        for level in 0..=tokens.len()
+        {
+            let lookahead = tokens.get(level).map(|token| token.symbol.clone());
+            let empty_span = tokens.get(level).map_or(end_span, |token| token.span).collapsed();
+
+            let mut changed = true;
+            while changed
+            {
+                changed = false;
+                let mut index = 0;
+                while index < frontier.len()
+                {
+                    let node = frontier[index];
+                    index += 1;
+
+                    let reductions = self.parse_table
+                        .get(&(stack[node].state, lookahead.clone()))
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|action| match action
+                        {
+                            Action::Reduce(rule) => Some(rule),
+                            _ => None
+                        });
+                    for (lhs, rhs_id) in reductions
+                    {
+                        let length = self.grammar.get_rhs(lhs, *rhs_id).unwrap().len();
+                        for (below, children) in paths(&stack, node, length)
+                        {
+                            let start = stack[below].level;
+                            let derived = *derivations.entry((lhs.clone(), start, level)).or_insert_with(|| {
+                                nodes.push(ForestNode::Symbol
+                                {
+                                    symbol: lhs.clone(),
+                                    start,
+                                    end: level,
+                                    span: if start == level { empty_span } else { tokens[start].span.to(&tokens[level - 1].span) },
+                                    alternatives: vec![]
+                                });
+                                nodes.len() - 1
+                            });
+                            if let ForestNode::Symbol { alternatives, .. } = &mut nodes[derived]
+                            {
+                                let packed = Packed { rhs_id: *rhs_id, children };
+                                if !alternatives.contains(&packed)
+                                {
+                                    alternatives.push(packed);
+                                    changed = true;
+                                }
+                            }
+
+                            match self.parse_table.get(&(stack[below].state, Some(lhs.clone()))).and_then(|actions| actions.first())
+                            {
+                                Some(Action::Shift(goto)) => {
+                                    let target = match stack_nodes.get(&(*goto, level))
+                                    {
+                                        Some(target) => *target,
+                                        None => {
+                                            stack.push(StackNode { state: *goto, level, edges: vec![] });
+                                            stack_nodes.insert((*goto, level), stack.len() - 1);
+                                            frontier.push(stack.len() - 1);
+                                            stack.len() - 1
+                                        }
+                                    };
+                                    if !stack[target].edges.contains(&(below, derived))
+                                    {
+                                        stack[target].edges.push((below, derived));
+                                        changed = true;
+                                    }
+                                },
+                                Some(Action::Accept) if level == tokens.len() => root = Some(derived),
+                                _ => ()
+                            }
+                        }
+                    }
+                }
+            }
+
+            let token = match tokens.get(level)
+            {
+                Some(token) => token,
+                None => break
+            };
+            nodes.push(ForestNode::Leaf(token.clone()));
+            let leaf = nodes.len() - 1;
+
+            let mut next_frontier = Vec::<usize>::new();
+            for node in frontier.iter()
+            {
+                for action in self.parse_table.get(&(stack[*node].state, lookahead.clone())).into_iter().flatten()
+                {
+                    if let Action::Shift(next_state) = action
+                    {
+                        let target = match stack_nodes.get(&(*next_state, level + 1))
+                        {
+                            Some(target) => *target,
+                            None => {
+                                stack.push(StackNode { state: *next_state, level: level + 1, edges: vec![] });
+                                stack_nodes.insert((*next_state, level + 1), stack.len() - 1);
+                                next_frontier.push(stack.len() - 1);
+                                stack.len() - 1
+                            }
+                        };
+                        stack[target].edges.push((*node, leaf));
+                    }
+                }
+            }
+
+            if next_frontier.is_empty()
+            {
+                return Err(Error::unexpected(&self.grammar, token.span, Some(token.symbol.clone()), self.expected(&stack, &frontier)));
+            }
+            frontier = next_frontier;
+        }
+
+        match root
+        {
+            Some(root) => Ok(Forest { nodes, root }),
+            None => Err(Error::unexpected(&self.grammar, end_span, None, self.expected(&stack, &frontier)))
+        }
+    }
+
+    // runs the actions over the derivation the disambiguator settles on
+    pub fn evaluate<T, A: Actions<T>, D: Disambiguator>(&self, forest: &Forest, actions: &mut A, disambiguator: &mut D) -> Result<T, Error>
+    {
+        let (value, span) = self.evaluate_node(forest, forest.root, actions, disambiguator, &mut vec![])?;
+        Ok(actions::finish(actions, &self.grammar.start, value, span))
+    }
+
+    fn evaluate_node<T, A: Actions<T>, D: Disambiguator>(&self, forest: &Forest, node: usize, actions: &mut A, disambiguator: &mut D, open: &mut Vec<usize>) -> Result<(Slot<T>, Span), Error>
+    {
+        let (symbol, span, alternatives) = match &forest.nodes[node]
+        {
+            ForestNode::Leaf(token) => return Ok((Slot::Value(actions.token(token)), token.span)),
+            ForestNode::Symbol { symbol, span, alternatives, .. } => (symbol, *span, alternatives)
+        };
+
+        // a derivation through a node still being evaluated would go round forever
+        open.push(node);
+        let allowed = alternatives
+            .iter()
+            .filter(|alternative| !alternative.children.iter().any(|child| open.contains(child)))
+            .filter(|alternative| disambiguator.allow(forest, node, alternative))
+            .collect::<Vec<&Packed>>();
+        let alternative = match allowed.len()
+        {
+            0 => return Err(Error::Parse
+            {
+                span,
+                found: None,
+                expected: vec![],
+                message: format!("Every derivation of {} was rejected.", symbol)
+            }),
+            1 => allowed[0],
+            count => match allowed.get(disambiguator.choose(forest, node, &allowed))
+            {
+                Some(alternative) => alternative,
+                None => return Err(Error::Parse
+                {
+                    span,
+                    found: None,
+                    expected: vec![],
+                    message: format!("The disambiguator chose none of the {} derivations of {}.", count, symbol)
+                })
+            }
+        };
+
+        let mut children = Vec::<(Slot<T>, Span)>::new();
+        for child in alternative.children.iter()
+        {
+            children.push(self.evaluate_node(forest, *child, actions, disambiguator, open)?);
+        }
+        open.pop();
+
+        // covering the children, as the LR parser does
+        let span = match (children.first(), children.last())
+        {
+            (Some((_, first)), Some((_, last))) => first.to(last),
+            _ => span
+        };
+        Ok(actions::reduce_slots(actions, &self.grammar, symbol, alternative.rhs_id, children, span))
+    }
+
+    // the terminals some parser in the frontier has an action for, None for the end of input
+    fn expected(&self, stack: &[StackNode], frontier: &[usize]) -> Vec<Option<Symbol>>
+    {
+        self.parse_table
+            .keys()
+            .filter(|(state, symbol)| frontier.iter().any(|node| stack[*node].state == *state)
+                && symbol.as_ref().is_none_or(|symbol| symbol.terminal && *symbol != Symbol::error()))
+            .map(|(_, symbol)| symbol.clone())
+            .collect()
+    }
+}
+
+// every way down length edges from node: the node reached and the forest nodes passed, in rhs order
+fn paths(stack: &[StackNode], node: usize, length: usize) -> Vec<(usize, Vec<usize>)>
+{
+    if length == 0
+    {
+        return vec![(node, vec![])];
+    }
+
+    let mut out = Vec::<(usize, Vec<usize>)>::new();
+    for (below, derived) in stack[node].edges.iter()
+    {
+        for (bottom, mut children) in paths(stack, *below, length - 1)
+        {
+            children.push(*derived);
+            out.push((bottom, children));
+        }
+    }
+    out
+}
+
+#[test]
+fn test_glr_ambiguous()
+{
+    let parser = GLRParser::new(Grammar::from_file("data/ambiguous").unwrap(), Mode::LALR).unwrap();
+
+    // num + num + num is a sum of either two sums, sharing the three numbers
+    let forest = parser.parse_forest(Token::split_whitespace("num + num + num $")).unwrap();
+    let ambiguities = forest.ambiguities();
+    assert_eq!(ambiguities.len(), 1);
+    match &forest.nodes[ambiguities[0]]
+    {
+        ForestNode::Symbol { symbol, start, end, alternatives, .. } => {
+            assert_eq!((symbol.label.as_str(), *start, *end, alternatives.len()), ("E", 0, 5, 2));
+        },
+        node => panic!("unexpected node {:?}", node)
+    }
+    assert_eq!(forest.nodes.iter().filter(|node| matches!(node, ForestNode::Leaf(_))).count(), 6);
+
+    // left associative: no sum as the right operand of a sum
+    struct LeftAssociative;
+    impl Disambiguator for LeftAssociative
+    {
+        fn allow(&mut self, forest: &Forest, _node: usize, alternative: &Packed) -> bool
+        {
+            match alternative.children.last().map(|child| &forest.nodes[*child])
+            {
+                Some(ForestNode::Symbol { alternatives, .. }) => alternative.rhs_id != 0 || alternatives.iter().all(|operand| operand.rhs_id != 0),
+                _ => true
+            }
+        }
+    }
+    let tree = parser.evaluate(&forest, &mut TreeBuilder, &mut LeftAssociative).unwrap();
+    let sum = &tree.children()[0];
+    assert_eq!((sum.children()[0].children().len(), sum.children()[2].children().len()), (3, 1));
+
+    // picking the other alternative nests the other way
+    struct Last;
+    impl Disambiguator for Last
+    {
+        fn choose(&mut self, _forest: &Forest, _node: usize, alternatives: &[&Packed]) -> usize
+        {
+            alternatives.len() - 1
+        }
+    }
+    let first = parser.evaluate(&forest, &mut TreeBuilder, &mut FirstAlternative).unwrap();
+    let last = parser.evaluate(&forest, &mut TreeBuilder, &mut Last).unwrap();
+    assert_ne!(first, last);
+    assert_eq!(first.lexemes(), last.lexemes());
+
+    // and a choice past the end fails instead of panicking
+    struct PastTheEnd;
+    impl Disambiguator for PastTheEnd
+    {
+        fn choose(&mut self, _forest: &Forest, _node: usize, alternatives: &[&Packed]) -> usize
+        {
+            alternatives.len()
+        }
+    }
+    match parser.evaluate(&forest, &mut TreeBuilder, &mut PastTheEnd)
+    {
+        Err(Error::Parse { message, .. }) => assert_eq!(message, "The disambiguator chose none of the 2 derivations of E."),
+        result => panic!("unexpected {:?}", result)
+    }
+
+    match parser.parse(String::from("num + + num $"))
+    {
+        Err(Error::Parse { found, expected, .. }) => {
+            assert_eq!(found, Some(Symbol::from(String::from("+"))));
+            assert_eq!(expected, vec![Some(Symbol::from(String::from("num")))]);
+        },
+        result => panic!("unexpected {:?}", result)
+    }
+}
+
+#[test]
+fn test_glr_not_lr()
+{
+    let grammar = Grammar::from_file("data/palindrome").unwrap();
+    assert!(matches!(LRParser::new(grammar.clone(), Mode::LR1), Err(Error::Conflicts(_))));
+    let parser = GLRParser::new(grammar, Mode::LR1).unwrap();
+
+    let tree = parser.parse(String::from("a a a a a $")).unwrap();
+    assert_eq!(tree.lexemes().len(), 6);
+    assert!(!parser.parse_forest(Token::split_whitespace("a a a $")).unwrap().is_ambiguous());
+    assert!(parser.parse(String::from("a a $")).is_err());
+
+    // dropping the LR(0) conflicts on non-terminal entries, here reducing the empty Statements where its goto is,
+    // loses no derivation the LALR table has
+    let grammar = Grammar::from_file("data/recovery").unwrap();
+    match LRParser::new(grammar.clone(), Mode::LR0)
+    {
+        Err(Error::Conflicts(conflicts)) => assert!(conflicts.iter().any(|conflict| matches!(conflict,
+            Conflict::ShiftReduce { symbol: Some(symbol), .. } if !symbol.terminal))),
+        _ => panic!("expected LR(0) conflicts")
+    }
+    let program = "id = num ; id = num ; $";
+    let lr0 = GLRParser::new(grammar.clone(), Mode::LR0).unwrap().parse_forest(Token::split_whitespace(program)).unwrap();
+    let lalr = GLRParser::new(grammar, Mode::LALR).unwrap().parse_forest(Token::split_whitespace(program)).unwrap();
+    assert_eq!(lr0, lalr);
+
+    // on an LR grammar the GLR parser builds what the LR parser does
+    let grammar = Grammar::from_file("data/bnf").unwrap();
+    let lr_parser = LRParser::new(grammar.clone(), Mode::LALR).unwrap();
+    let parser = GLRParser::new(grammar, Mode::LALR).unwrap();
+    assert_eq!(parser.parse(String::from("a b b d c $")).unwrap(), lr_parser.parse(String::from("a b b d c $")).unwrap());
+}
@@ -1,182 +1,520 @@
 use std::collections::{HashMap, VecDeque, HashSet};
 use std::fs::read_to_string;
+use std::str::FromStr;
 use crate::symbol::Symbol;
+use crate::error::Error;
 
 #[derive(Debug, Clone)]
+struct GrammarToken
+{
+    text: String,
+    // quoted text is always a terminal, never punctuation or a declaration
+    quoted: bool,
+    line: usize,
+    column: usize
+}
+
+// nonterminals generated for the EBNF operators; the parsers flatten Repeat and Optional back into lists
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Helper
+{
+    // X* or X+, where X is a Group helper if it was parenthesized
+    Repeat
+    {
+        element: Symbol,
+        at_least_one: bool
+    },
+    // X?
+    Optional
+    {
+        element: Symbol
+    },
+    // ( ... | ... ), one production per alternative
+    Group
+}
+
+// a right hand side as written, with its %prec terminal if any
+type Alternative = (Vec<Symbol>, Option<Symbol>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity
+{
+    Left,
+    Right,
+    NonAssoc
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Grammar
 {
-    tokens_iter:VecDeque<String>,
+    // names declared with %token, terminals whatever their case
+    pub(crate) declared_tokens: HashSet<String>,
+    pub start: Symbol,
     pub productions: HashMap<Symbol, Vec<Vec<Symbol>>>,
+    // lhs symbols in the order their rules were declared, helpers where their EBNF first appeared
+    pub(crate) order: Vec<Symbol>,
     pub nonterminals: HashSet<Symbol>,
     pub terminals: HashSet<Symbol>,
-    pub lambda_deriving_symbols: HashSet<Symbol> 
+    // nullable nonterminals, and FIRST and FOLLOW of every symbol, kept by compute_sets
+    lambda_deriving_symbols: HashSet<Symbol>,
+    first_sets: HashMap<Symbol, HashSet<Symbol>>,
+    follow_sets: HashMap<Symbol, HashSet<Symbol>>,
+    // named after the EBNF they stand for, like "Item*" or "(',' Item)", which no bare word can be
+    pub helpers: HashMap<Symbol, Helper>,
+    // from %left, %right and %nonassoc; later declarations get higher levels and bind tighter
+    pub precedence: HashMap<Symbol, (u32, Associativity)>,
+    // the %prec terminal of a production, by lhs and rhs_id
+    pub production_precedence: HashMap<(Symbol, u32), Symbol>,
+    // from %name, what parse errors call a terminal
+    pub display_names: HashMap<Symbol, String>
 }
 
 impl Grammar
 {
-    pub fn from_file(filename: &str) -> Grammar
+    pub fn from_file(filename: &str) -> Result<Grammar, Error>
     {
-        let tokens_iter = read_to_string(filename).unwrap().split_whitespace().map(|x| x.to_string() ).collect();
+        let text = read_to_string(filename).map_err(|error| Error::Io
+            {
+                filename: filename.to_string(),
+                message: error.to_string()
+            })?;
 
-        let mut grammar = Grammar
+        text.parse::<Grammar>()
+    }
+
+    pub(crate) fn empty() -> Grammar
+    {
+        Grammar
         {
-            tokens_iter: tokens_iter,
+            declared_tokens: HashSet::<String>::new(),
+            start: Symbol
+            {
+                label: String::from("Start"),
+                terminal: false
+            },
             productions: HashMap::<Symbol, Vec<Vec<Symbol>>>::new(),
+            order: Vec::<Symbol>::new(),
             nonterminals: HashSet::<Symbol>::new(),
             terminals: HashSet::<Symbol>::new(),
-            lambda_deriving_symbols: HashSet::<Symbol>::new()
+            lambda_deriving_symbols: HashSet::<Symbol>::new(),
+            first_sets: HashMap::<Symbol, HashSet<Symbol>>::new(),
+            follow_sets: HashMap::<Symbol, HashSet<Symbol>>::new(),
+            helpers: HashMap::<Symbol, Helper>::new(),
+            precedence: HashMap::<Symbol, (u32, Associativity)>::new(),
+            production_precedence: HashMap::<(Symbol, u32), Symbol>::new(),
+            display_names: HashMap::<Symbol, String>::new()
+        }
+    }
+
+    // adds the rules, merged by lhs in first appearance order, and finishes the grammar the same way
+    // whether it was read or built: picks the start symbol, collects the symbols, checks and computes the sets
+    pub(crate) fn install(&mut self, rules: Vec<(Symbol, Vec<Vec<Symbol>>)>, declared_start: Option<String>) -> Result<(), Error>
+    {
+        // without %start, Start if there is one, otherwise the first rule
+        let start_label = declared_start.unwrap_or_else(|| {
+            if rules.iter().any(|(lhs, _)| lhs.label == "Start") || rules.is_empty()
+            {
+                String::from("Start")
+            }
+            else
+            {
+                rules[0].0.label.clone()
+            }
+        });
+        self.start = Symbol
+        {
+            label: start_label,
+            terminal: false
         };
-        grammar.parse();
-        grammar.generate_lambda_set();
 
-        grammar
+        for (lhs, prod_list) in rules.into_iter()
+        {
+            if !self.order.contains(&lhs)
+            {
+                self.order.push(lhs.clone());
+            }
+            self.productions.entry(lhs).or_default().extend(prod_list);
+        }
+        for symbol in self.productions.iter().flat_map(|(lhs, prod_list)| std::iter::once(lhs).chain(prod_list.iter().flatten()))
+        {
+            match symbol.terminal
+            {
+                true => self.terminals.insert(symbol.clone()),
+                false => self.nonterminals.insert(symbol.clone())
+            };
+        }
+
+        self.check_definitions()?;
+        self.compute_sets();
+        Ok(())
+    }
+
+    // every lhs, in declaration order, then any added since in label order
+    pub fn rule_order(&self) -> Vec<Symbol>
+    {
+        let mut order = self.order
+            .iter()
+            .filter(|lhs| self.productions.contains_key(lhs))
+            .cloned()
+            .collect::<Vec<Symbol>>();
+        let mut rest = self.productions
+            .keys()
+            .filter(|lhs| !self.order.contains(lhs))
+            .cloned()
+            .collect::<Vec<Symbol>>();
+        rest.sort();
+        order.extend(rest);
+        order
+    }
+
+    // how parse errors show a terminal: its %name, otherwise its label in backticks
+    pub fn display_name(&self, symbol: &Symbol) -> String
+    {
+        match self.display_names.get(symbol)
+        {
+            Some(name) => name.clone(),
+            None => format!("`{}`", symbol.label)
+        }
     }
 
     pub fn get_rhs(&self, lhs: &Symbol, rhs_id: u32) -> Option<&Vec<Symbol>>
     {
-        self.productions.get(&lhs).map(|list| &list[rhs_id as usize])
+        self.productions.get(lhs).and_then(|list| list.get(rhs_id as usize))
     }
 
-    fn parse(&mut self)
+    // the %prec terminal's precedence, otherwise that of the rightmost terminal with one
+    pub fn rule_precedence(&self, lhs: &Symbol, rhs_id: u32) -> Option<(u32, Associativity)>
     {
-        let mut pre_hash_map =  Vec::<(Symbol, Vec<Vec<Symbol>>)>::new();
-        
-        // collect associated productions before building hashmap
-        while !self.tokens_iter.is_empty()
+        if let Some(symbol) = self.production_precedence.get(&(lhs.clone(), rhs_id))
         {
-            let (new_lhs, mut new_prod_list) = self.parse_rule();
-            let mut found_index: Option<usize> = None;
-            for i in 0..pre_hash_map.len()
-            {
-                if pre_hash_map[i].0 == new_lhs
-                {
-                    found_index = Some(i);
-                }
-            }
+            return self.precedence.get(symbol).copied();
+        }
 
-            if let Some(index) = found_index
-            {
-                pre_hash_map[index].1.append(&mut new_prod_list);
-            }
-            else
-            {
-                pre_hash_map.push( (new_lhs, new_prod_list) );
-            }
+        self.get_rhs(lhs, rhs_id)?
+            .iter()
+            .rev()
+            .find_map(|symbol| self.precedence.get(symbol))
+            .copied()
+    }
+
+    // every nonterminal, including the start symbol, needs at least one production
+    fn check_definitions(&self) -> Result<(), Error>
+    {
+        if !self.productions.contains_key(&self.start)
+        {
+            return Err(Error::UndefinedNonterminal(self.start.clone()));
         }
-        let pre_hash_map = pre_hash_map; // freeze
 
-        // build the hashmap
-        for (lhs, prod_list) in pre_hash_map.into_iter()
+        let mut undefined = self.nonterminals
+            .iter()
+            .filter(|symbol| !self.productions.contains_key(symbol))
+            .collect::<Vec<&Symbol>>();
+        undefined.sort();
+
+        match undefined.first()
         {
-            self.productions.insert( lhs, prod_list );
+            Some(symbol) => Err(Error::UndefinedNonterminal((*symbol).clone())),
+            None => Ok(())
         }
+    }
 
+    // EBNF operators are never part of a bare word, so terminals spelled with them must be quoted
+    fn is_operator(c: char) -> bool
+    {
+        matches!(c, '(' | ')' | '*' | '+' | '?')
     }
 
-    fn read_symbol(&mut self) -> Symbol
+    fn is_punctuation(token: &GrammarToken) -> bool
     {
-        let symbol = Symbol::from(self.next());
+        !token.quoted && (token.text == "->" || token.text == "|" || token.text == ";" || token.text.starts_with('%')
+            || token.text.chars().all(Grammar::is_operator))
+    }
 
-        if symbol.terminal
+    fn classify(&self, token: &GrammarToken) -> Symbol
+    {
+        if token.quoted || self.declared_tokens.contains(&token.text)
         {
-            self.terminals.insert(symbol.clone());
+            Symbol
+            {
+                label: token.text.clone(),
+                terminal: true
+            }
         }
         else
         {
-            self.nonterminals.insert(symbol.clone());
+            Symbol::from(token.text.clone())
         }
-
-        symbol
     }
 
-    fn parse_rule(&mut self) -> (Symbol, Vec<Vec<Symbol>>)
+    // how the symbol is written in a grammar file, quoted if a bare word would read differently
+    pub(crate) fn source_text(&self, symbol: &Symbol) -> String
     {
-        let lhs = self.read_symbol();
-
-        self.expect("->");
-        let mut prod_list = Vec::<Vec<Symbol>>::new();
-        prod_list.push(self.parse_rhs());
+        if self.helpers.contains_key(symbol)
+        {
+            return symbol.label.clone();
+        }
 
-        while self.next_symbol_is("|")
+        let bare = GrammarToken
         {
-            self.expect("|");
+            text: symbol.label.clone(),
+            quoted: false,
+            line: 0,
+            column: 0
+        };
+        let needs_quotes = symbol.terminal && (Grammar::is_punctuation(&bare)
+            || self.classify(&bare) != *symbol
+            || symbol.label.contains("//")
+            || symbol.label.chars().any(|c| c.is_whitespace() || c == '\'' || c == '"' || c == '#' || Grammar::is_operator(c)));
 
-            prod_list.push(self.parse_rhs());
+        if needs_quotes
+        {
+            format!("'{}'", symbol.label.replace('\\', "\\\\").replace('\'', "\\'"))
+        }
+        else
+        {
+            symbol.label.clone()
         }
-        self.expect(";");
+    }
 
-        (lhs, prod_list)
+    // the productions of a Repeat or Optional helper: left recursive, or right recursive given the X* helper that X+ continues with
+    fn helper_productions(helper_symbol: &Symbol, helper: &Helper, right_tail: Option<&Symbol>) -> Vec<Vec<Symbol>>
+    {
+        match (helper, right_tail)
+        {
+            (Helper::Repeat { element, at_least_one: false }, None) => vec![vec![helper_symbol.clone(), element.clone()], vec![]],
+            (Helper::Repeat { element, at_least_one: true }, None) => vec![vec![helper_symbol.clone(), element.clone()], vec![element.clone()]],
+            (Helper::Repeat { element, at_least_one: false }, Some(_)) => vec![vec![element.clone(), helper_symbol.clone()], vec![]],
+            (Helper::Repeat { element, at_least_one: true }, Some(tail)) => vec![vec![element.clone(), tail.clone()]],
+            (Helper::Optional { element }, _) => vec![vec![element.clone()], vec![]],
+            (Helper::Group, _) => panic!("group productions are their alternatives")
+        }
     }
 
-    fn peek(&self) -> Option<&String>
+    // reuses the helper if the same EBNF appeared before
+    fn add_helper(&mut self, label: String, helper: Helper, productions: Vec<Vec<Symbol>>) -> Symbol
     {
-        self.tokens_iter.front()
+        let symbol = Symbol
+        {
+            label,
+            terminal: false
+        };
+        if !self.helpers.contains_key(&symbol)
+        {
+            self.nonterminals.insert(symbol.clone());
+            self.productions.insert(symbol.clone(), productions);
+            self.order.push(symbol.clone());
+            self.helpers.insert(symbol.clone(), helper);
+        }
+        symbol
     }
 
-    fn parse_rhs(&mut self) -> Vec<Symbol>
+    // the same grammar with X* and X+ recursing to the right, as a top down parser needs
+    pub fn right_recursive(&self) -> Grammar
     {
-        // assert_eq!(peek()
-        let mut out = Vec::<Symbol>::new();
+        let mut grammar = self.clone();
+
+        // X+ becomes X X*, so every X+ needs an X*
+        for helper in self.helpers.values()
+        {
+            if let Helper::Repeat { element, at_least_one: true } = helper
+            {
+                let label = format!("{}*", grammar.source_text(element));
+                let star = Helper::Repeat { element: element.clone(), at_least_one: false };
+                grammar.add_helper(label, star, vec![]);
+            }
+        }
 
-        while !self.next_symbol_is(";") && !self.next_symbol_is("|")
+        let repeats = grammar.helpers
+            .iter()
+            .filter(|(_, helper)| matches!(helper, Helper::Repeat { .. }))
+            .map(|(symbol, helper)| (symbol.clone(), helper.clone()))
+            .collect::<Vec<(Symbol, Helper)>>();
+        for (symbol, helper) in repeats
         {
-            out.push(self.read_symbol());
+            let tail = match &helper
+            {
+                Helper::Repeat { element, at_least_one: true } => Symbol
+                {
+                    label: format!("{}*", grammar.source_text(element)),
+                    terminal: false
+                },
+                _ => symbol.clone()
+            };
+            let productions = Grammar::helper_productions(&symbol, &helper, Some(&tail));
+            grammar.productions.insert(symbol, productions);
         }
 
-        out
+        grammar.compute_sets();
+        grammar
     }
 
-    fn next_symbol_is(&self, expected: &str) -> bool
+    // nullable, FIRST and FOLLOW of every symbol, each by worklist to a fixed point;
+    // call again after editing the productions by hand
+    pub fn compute_sets(&mut self)
+    {
+        self.lambda_deriving_symbols = self.compute_nullable();
+        self.first_sets = self.compute_first();
+        self.follow_sets = self.compute_follow();
+    }
+
+    pub fn lambda_deriving_symbols(&self) -> &HashSet<Symbol>
+    {
+        &self.lambda_deriving_symbols
+    }
+
+    // for nonterminals; a terminal's FIRST is itself
+    pub fn first_sets(&self) -> &HashMap<Symbol, HashSet<Symbol>>
     {
-        self.peek() == Some(&expected.to_string())
+        &self.first_sets
     }
 
-    fn next(&mut self) -> String 
+    // the end of the input is left out, SLR adds it where needed
+    pub fn follow_sets(&self) -> &HashMap<Symbol, HashSet<Symbol>>
     {
-        self.tokens_iter.pop_front().unwrap()
+        &self.follow_sets
     }
 
-    fn expect(&mut self, expected: &str)
+    fn compute_nullable(&self) -> HashSet<Symbol>
     {
-        let last = self.next();
-        assert_eq!(&last[..], expected);
+        // how many symbols of each production are not known to be nullable yet, and where each symbol occurs
+        let mut waiting = HashMap::<(Symbol, usize), usize>::new();
+        let mut occurrences = HashMap::<Symbol, Vec<(Symbol, usize)>>::new();
+        let mut nullable = HashSet::<Symbol>::new();
+        let mut work_list = Vec::<Symbol>::new();
+
+        for (lhs, prod_list) in &self.productions
+        {
+            for (rhs_id, rhs) in prod_list.iter().enumerate()
+            {
+                if rhs.iter().any(|symbol| symbol.terminal)
+                {
+                    continue;
+                }
+                waiting.insert((lhs.clone(), rhs_id), rhs.len());
+                for symbol in rhs
+                {
+                    occurrences.entry(symbol.clone()).or_default().push((lhs.clone(), rhs_id));
+                }
+                if rhs.is_empty() && nullable.insert(lhs.clone())
+                {
+                    work_list.push(lhs.clone());
+                }
+            }
+        }
+
+        while let Some(symbol) = work_list.pop()
+        {
+            for (lhs, rhs_id) in occurrences.get(&symbol).into_iter().flatten()
+            {
+                let count = waiting.get_mut(&(lhs.clone(), *rhs_id)).unwrap();
+                *count -= 1;
+                if *count == 0 && nullable.insert(lhs.clone())
+                {
+                    work_list.push(lhs.clone());
+                }
+            }
+        }
+        nullable
     }
 
-    fn generate_lambda_set(&mut self)
+    fn compute_first(&self) -> HashMap<Symbol, HashSet<Symbol>>
     {
-        let mut previous_size = 0;
-        'prod_list: for (lhs, prod_list) in &self.productions
+        // whose FIRST has to be looked at again when a nonterminal's grows
+        let mut users = HashMap::<Symbol, HashSet<Symbol>>::new();
+        for (lhs, prod_list) in &self.productions
         {
-            for prod in prod_list
+            for rhs in prod_list
             {
-                if prod.is_empty()
+                for symbol in rhs.iter().take_while(|symbol| !symbol.terminal)
                 {
-                    self.lambda_deriving_symbols.insert(lhs.clone());
-                    continue 'prod_list;
+                    users.entry(symbol.clone()).or_default().insert(lhs.clone());
+                    if !self.lambda_deriving_symbols.contains(symbol)
+                    {
+                        break;
+                    }
                 }
             }
         }
-        while self.lambda_deriving_symbols.len() != previous_size
+
+        let mut first = self.productions
+            .keys()
+            .map(|lhs| (lhs.clone(), HashSet::<Symbol>::new()))
+            .collect::<HashMap<Symbol, HashSet<Symbol>>>();
+        let mut work_list = self.productions.keys().cloned().collect::<Vec<Symbol>>();
+        let mut queued = work_list.iter().cloned().collect::<HashSet<Symbol>>();
+
+        while let Some(lhs) = work_list.pop()
         {
-            previous_size = self.lambda_deriving_symbols.len().clone();
-            for (lhs, prod_list) in &self.productions
+            queued.remove(&lhs);
+            let mut out = HashSet::<Symbol>::new();
+            for rhs in &self.productions[&lhs]
+            {
+                out.extend(self.first_of_sequence(rhs, &first));
+            }
+
+            // FIRST only grows, so a change shows in the size
+            if out.len() != first[&lhs].len()
+            {
+                first.insert(lhs.clone(), out);
+                for user in users.get(&lhs).into_iter().flatten()
+                {
+                    if queued.insert(user.clone())
+                    {
+                        work_list.push(user.clone());
+                    }
+                }
+            }
+        }
+        first
+    }
+
+    fn compute_follow(&self) -> HashMap<Symbol, HashSet<Symbol>>
+    {
+        let mut follow = HashMap::<Symbol, HashSet<Symbol>>::new();
+        // FOLLOW(lhs) flows into FOLLOW(symbol) wherever only nullable symbols come after symbol
+        let mut flows = HashMap::<Symbol, HashSet<Symbol>>::new();
+
+        for (lhs, prod_list) in &self.productions
+        {
+            for rhs in prod_list
             {
-                for prod in prod_list
+                for (index, symbol) in rhs.iter().enumerate()
                 {
-                    if self.rhs_derives_lambda(prod)
+                    let rest = &rhs[index + 1..];
+                    follow.entry(symbol.clone()).or_default().extend(self.first_of_sequence(rest, &self.first_sets));
+                    if rest.iter().all(|symbol| self.lambda_deriving_symbols.contains(symbol)) && symbol != lhs
                     {
-                        self.lambda_deriving_symbols.insert(lhs.clone());
+                        flows.entry(lhs.clone()).or_default().insert(symbol.clone());
                     }
                 }
             }
+        }
 
+        let mut work_list = follow.keys().cloned().collect::<Vec<Symbol>>();
+        let mut queued = work_list.iter().cloned().collect::<HashSet<Symbol>>();
+        while let Some(symbol) = work_list.pop()
+        {
+            queued.remove(&symbol);
+            let out = follow.get(&symbol).cloned().unwrap_or_default();
+            for target in flows.get(&symbol).into_iter().flatten()
+            {
+                let target_follow = follow.entry(target.clone()).or_default();
+                let before = target_follow.len();
+                target_follow.extend(out.iter().cloned());
+                if target_follow.len() != before && queued.insert(target.clone())
+                {
+                    work_list.push(target.clone());
+                }
+            }
         }
+        follow
     }
+
     pub fn rhs_derives_lambda(&self, rhs: &Vec<Symbol>) -> bool
     {
         for symbol in rhs
         {
-            if !self.lambda_deriving_symbols.contains(&symbol)
+            if !self.lambda_deriving_symbols.contains(symbol)
             {
                 return false;
             }
@@ -186,117 +524,722 @@ impl Grammar
 
     pub fn follow(&self, s: &Symbol) -> HashSet<Symbol>
     {
-        let mut visited = HashSet::<Symbol>::new();
-        self.inner_follow(s, &mut visited)
+        self.follow_sets.get(s).cloned().unwrap_or_default()
     }
 
-    fn inner_follow(&self, s: &Symbol, visited: &mut HashSet<Symbol>) -> HashSet<Symbol>
+    // FIRST_k of every nonterminal by fixed point: the strings of at most k terminals its derivations
+    // begin with, shorter ones only where the whole derivation is that short
+    pub fn first_k(&self, k: usize) -> HashMap<Symbol, HashSet<Vec<Symbol>>>
     {
-        let mut out = HashSet::<Symbol>::new();
-        if visited.contains(s)
+        let mut first = self.productions
+            .keys()
+            .map(|lhs| (lhs.clone(), HashSet::<Vec<Symbol>>::new()))
+            .collect::<HashMap<Symbol, HashSet<Vec<Symbol>>>>();
+
+        let mut changed = true;
+        while changed
         {
-            return out;
+            changed = false;
+            for (lhs, prod_list) in &self.productions
+            {
+                for rhs in prod_list
+                {
+                    for string in Grammar::first_k_of_rhs(rhs, k, &first)
+                    {
+                        changed |= first.get_mut(lhs).unwrap().insert(string);
+                    }
+                }
+            }
         }
-        visited.insert(s.clone());
-        for (lhs, prod_list) in &self.productions
+        first
+    }
+
+    // FIRST_k of a sequence of symbols given FIRST_k of the nonterminals
+    pub fn first_k_of_rhs(rhs: &[Symbol], k: usize, first: &HashMap<Symbol, HashSet<Vec<Symbol>>>) -> HashSet<Vec<Symbol>>
+    {
+        let mut out = vec![vec![]].into_iter().collect::<HashSet<Vec<Symbol>>>();
+        for symbol in rhs
+        {
+            if out.iter().all(|string| string.len() >= k)
+            {
+                break;
+            }
+            let next = match symbol.terminal
+            {
+                true => vec![vec![symbol.clone()]].into_iter().collect(),
+                false => first.get(symbol).cloned().unwrap_or_default()
+            };
+            out = Grammar::concat_k(&out, &next, k);
+        }
+        out
+    }
+
+    // FOLLOW_k of every nonterminal; the empty string stands for the end of the input after the start symbol
+    pub fn follow_k(&self, k: usize, first: &HashMap<Symbol, HashSet<Vec<Symbol>>>) -> HashMap<Symbol, HashSet<Vec<Symbol>>>
+    {
+        let mut follow = self.productions
+            .keys()
+            .map(|lhs| (lhs.clone(), HashSet::<Vec<Symbol>>::new()))
+            .collect::<HashMap<Symbol, HashSet<Vec<Symbol>>>>();
+        follow.entry(self.start.clone()).or_default().insert(vec![]);
+
+        let mut changed = true;
+        while changed
         {
-            for prod in prod_list
+            changed = false;
+            for (lhs, prod_list) in &self.productions
             {
-                for (index, symbol) in prod.iter().enumerate()
+                for rhs in prod_list
                 {
-                    if symbol == s
+                    for (index, symbol) in rhs.iter().enumerate().filter(|(_, symbol)| !symbol.terminal)
                     {
-                        let reduced_rhs = &prod[index + 1..].to_vec();
-                        for first in self.first_of_rhs(reduced_rhs).into_iter()
+                        let rest = Grammar::first_k_of_rhs(&rhs[index + 1..], k, first);
+                        let after = Grammar::concat_k(&rest, &follow[lhs], k);
+                        let symbol_follow = follow.entry(symbol.clone()).or_default();
+                        for string in after
                         {
-                            out.insert(first);
+                            changed |= symbol_follow.insert(string);
                         }
-                        if self.rhs_derives_lambda(reduced_rhs)
-                        {
-                            for follow in self.inner_follow(lhs, visited ).into_iter()
-                            {
-                                out.insert(follow);
-                            }
-                        }
-
                     }
-
                 }
+            }
+        }
+        follow
+    }
 
+    // every string of left followed by one of right, cut to k symbols
+    pub fn concat_k(left: &HashSet<Vec<Symbol>>, right: &HashSet<Vec<Symbol>>, k: usize) -> HashSet<Vec<Symbol>>
+    {
+        let mut out = HashSet::<Vec<Symbol>>::new();
+        for string in left
+        {
+            if string.len() >= k
+            {
+                out.insert(string[..k].to_vec());
+                continue;
+            }
+            for rest in right
+            {
+                let mut joined = string.clone();
+                joined.extend(rest.iter().take(k - string.len()).cloned());
+                out.insert(joined);
             }
         }
         out
     }
 
-    pub fn first_of_rhs(&self, rhs: &Vec<Symbol>) -> HashSet<Symbol>
+    pub fn first_of_rhs(&self, rhs: &[Symbol]) -> HashSet<Symbol>
+    {
+        self.first_of_sequence(rhs, &self.first_sets)
+    }
+
+    pub fn first_of_symbol(&self, s: &Symbol) -> HashSet<Symbol>
+    {
+        if s.terminal
+        {
+            return vec![s.clone()].into_iter().collect();
+        }
+        self.first_sets.get(s).cloned().unwrap_or_default()
+    }
+
+    fn first_of_sequence(&self, rhs: &[Symbol], first: &HashMap<Symbol, HashSet<Symbol>>) -> HashSet<Symbol>
     {
         let mut out = HashSet::<Symbol>::new();
-        'symbol: for symbol in rhs 
+        for symbol in rhs
         {
             if symbol.terminal
             {
                 out.insert(symbol.clone());
-                break 'symbol;
+                break;
+            }
+
+            // an undefined nonterminal derives nothing
+            out.extend(first.get(symbol).into_iter().flatten().cloned());
+            if !self.lambda_deriving_symbols.contains(symbol)
+            {
+                break;
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for Grammar
+{
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Grammar, Error>
+    {
+        let (tokens_iter, end) = GrammarReader::tokenize(text)?;
+        let reader = GrammarReader
+        {
+            tokens_iter,
+            end,
+            grammar: Grammar::empty()
+        };
+        reader.parse()
+    }
+}
+
+// the grammar file parser, filling in a grammar as it reads
+struct GrammarReader
+{
+    tokens_iter: VecDeque<GrammarToken>,
+    // position just past the last token, for errors at end of file
+    end: (usize, usize),
+    grammar: Grammar
+}
+
+impl GrammarReader
+{
+    // splits the text into words and quoted literals, dropping # and // comments
+    fn tokenize(text: &str) -> Result<(VecDeque<GrammarToken>, (usize, usize)), Error>
+    {
+        let mut tokens = VecDeque::<GrammarToken>::new();
+        let chars = text.chars().collect::<Vec<char>>();
+        let mut index = 0;
+        let mut line = 1;
+        let mut column = 1;
+
+        while index < chars.len()
+        {
+            let c = chars[index];
+            let (token_line, token_column) = (line, column);
+
+            if c.is_whitespace()
+            {
+                index += 1;
+                if c == '\n'
+                {
+                    line += 1;
+                    column = 1;
+                }
+                else
+                {
+                    column += 1;
+                }
+            }
+            else if c == '#' || (c == '/' && chars.get(index + 1) == Some(&'/'))
+            {
+                while index < chars.len() && chars[index] != '\n'
+                {
+                    index += 1;
+                    column += 1;
+                }
+            }
+            else if c == '\'' || c == '"'
+            {
+                let mut literal = String::new();
+                index += 1;
+                column += 1;
+                loop
+                {
+                    let next = match chars.get(index)
+                    {
+                        Some('\n') | None => return Err(Error::Syntax
+                        {
+                            line: token_line,
+                            column: token_column,
+                            message: String::from("unterminated quoted terminal")
+                        }),
+                        Some(next) => *next
+                    };
+                    index += 1;
+                    column += 1;
+
+                    if next == c
+                    {
+                        break;
+                    }
+                    if next == '\\' && index < chars.len() && chars[index] != '\n'
+                    {
+                        literal.push(chars[index]);
+                        index += 1;
+                        column += 1;
+                    }
+                    else
+                    {
+                        literal.push(next);
+                    }
+                }
+
+                if literal.is_empty()
+                {
+                    return Err(Error::Syntax
+                    {
+                        line: token_line,
+                        column: token_column,
+                        message: String::from("empty quoted terminal")
+                    });
+                }
+                tokens.push_back(GrammarToken
+                {
+                    text: literal,
+                    quoted: true,
+                    line: token_line,
+                    column: token_column
+                });
+            }
+            else if Grammar::is_operator(c)
+            {
+                index += 1;
+                column += 1;
+                tokens.push_back(GrammarToken
+                {
+                    text: c.to_string(),
+                    quoted: false,
+                    line: token_line,
+                    column: token_column
+                });
             }
             else
             {
-                for first in self.first_of_symbol(symbol).into_iter()
+                let mut word = String::new();
+                while index < chars.len() && !chars[index].is_whitespace() && chars[index] != '\'' && chars[index] != '"' && !Grammar::is_operator(chars[index])
                 {
-                    out.insert(first);
+                    word.push(chars[index]);
+                    index += 1;
+                    column += 1;
                 }
-                if !self.lambda_deriving_symbols.contains(symbol)
+                for _ in 0..GrammarReader::primes_at(&chars, index)
                 {
-                    break 'symbol;
+                    word.push('\'');
+                    index += 1;
+                    column += 1;
                 }
+                tokens.push_back(GrammarToken
+                {
+                    text: word,
+                    quoted: false,
+                    line: token_line,
+                    column: token_column
+                });
             }
         }
-        out
+
+        Ok((tokens, (line, column)))
     }
 
-    pub fn first_of_symbol(&self, s: &Symbol) -> HashSet<Symbol>
+    // the primes that end a word, as in the E' that transforms name their non-terminals, unless they
+    // open a quoted terminal: they must be followed by whitespace, the end, or punctuation that is not
+    fn primes_at(chars: &[char], index: usize) -> usize
     {
-        let mut visited = HashSet::<Symbol>::new();
-        self.inner_first_of_symbol(s, &mut visited)
+        let primes = chars[index..].iter().take_while(|c| **c == '\'').count();
+        let ends_word = match chars.get(index + primes)
+        {
+            None => true,
+            Some(c) if c.is_whitespace() => true,
+            Some(';') | Some('|') | Some(')') => chars.get(index + primes + 1) != Some(&'\''),
+            Some(_) => false
+        };
+        if ends_word { primes } else { 0 }
     }
 
-    fn inner_first_of_symbol(&self, s: &Symbol, visited: &mut HashSet<Symbol>) -> HashSet<Symbol>
+    fn parse(mut self) -> Result<Grammar, Error>
     {
-        let mut out = HashSet::<Symbol>::new();
-        if visited.contains(s)
-        {
-            return out;
-        }
-        visited.insert(s.clone());
-        if s.terminal
+        let mut pre_hash_map =  Vec::<(Symbol, Vec<Vec<Symbol>>)>::new();
+        let mut declared_start: Option<String> = None;
+        
+        // collect associated productions before building hashmap
+        while let Some(token) = self.tokens_iter.front()
         {
-            out.insert(s.clone());
-            return out;
+            if !token.quoted && token.text.starts_with('%')
+            {
+                if !pre_hash_map.is_empty()
+                {
+                    return Err(self.syntax_error(String::from("declarations must come before the rules")));
+                }
+                if let Some(start) = self.parse_declaration()?
+                {
+                    declared_start = Some(start);
+                }
+                continue;
+            }
+
+            let (new_lhs, new_prod_list) = self.parse_rule()?;
+            let found_index = pre_hash_map.iter().position(|(lhs, _)| *lhs == new_lhs);
+            let index = found_index.unwrap_or_else(|| {
+                pre_hash_map.push( (new_lhs.clone(), vec![]) );
+                pre_hash_map.len() - 1
+            });
+
+            for (rhs, prec) in new_prod_list
+            {
+                if let Some(prec) = prec
+                {
+                    self.grammar.production_precedence.insert((new_lhs.clone(), pre_hash_map[index].1.len() as u32), prec);
+                }
+                pre_hash_map[index].1.push(rhs);
+            }
         }
+        self.grammar.install(pre_hash_map, declared_start)?;
+
+        Ok(self.grammar)
+    }
 
-        for rule in &self.productions[s]
+    // %token names... ; %start name ; or %left, %right, %nonassoc names... ;
+    // returning the start symbol's name for %start
+    fn parse_declaration(&mut self) -> Result<Option<String>, Error>
+    {
+        let keyword = self.tokens_iter.front().map(|token| token.text.clone()).unwrap_or_default();
+        match &keyword[..]
         {
-            'symbol: for symbol in rule
-            {
-                if symbol.terminal
+            "%token" => {
+                self.next()?;
+                while !self.next_symbol_is(";")
                 {
-                    out.insert(symbol.clone());
-                    break 'symbol;
+                    let name = self.read_name()?;
+                    self.grammar.declared_tokens.insert(name);
                 }
-                else
+                self.expect(";")?;
+                Ok(None)
+            },
+            "%start" => {
+                self.next()?;
+                if self.tokens_iter.front().is_some_and(|token| token.quoted || self.grammar.declared_tokens.contains(&token.text))
+                {
+                    return Err(self.syntax_error(String::from("the start symbol must be a non-terminal")));
+                }
+                let name = self.read_name()?;
+                self.expect(";")?;
+                Ok(Some(name))
+            },
+            "%left" | "%right" | "%nonassoc" => {
+                self.next()?;
+                let associativity = match &keyword[..]
+                {
+                    "%left" => Associativity::Left,
+                    "%right" => Associativity::Right,
+                    _ => Associativity::NonAssoc
+                };
+                let level = self.grammar.precedence.values().map(|(level, _)| *level).max().unwrap_or(0) + 1;
+                while !self.next_symbol_is(";")
                 {
-                    for first in self.inner_first_of_symbol(symbol, visited).into_iter()
+                    let name = self.read_name()?;
+                    self.grammar.declared_tokens.insert(name.clone());
+                    self.grammar.precedence.insert(Symbol { label: name, terminal: true }, (level, associativity));
+                }
+                self.expect(";")?;
+                Ok(None)
+            },
+            "%name" => {
+                self.next()?;
+                while !self.next_symbol_is(";")
+                {
+                    let symbol = self.read_symbol()?;
+                    if !symbol.terminal
                     {
-                        out.insert(first);
+                        return Err(self.syntax_error(format!("only terminals have display names, not {}", symbol)));
                     }
-                    if !self.lambda_deriving_symbols.contains(symbol)
+                    if !self.tokens_iter.front().is_some_and(|token| token.quoted)
                     {
-                        break 'symbol;
+                        return Err(self.syntax_error(format!("expected a quoted display name for {}", symbol)));
                     }
+                    let name = self.next()?;
+                    self.grammar.display_names.insert(symbol, name);
                 }
+                self.expect(";")?;
+                Ok(None)
+            },
+            _ => Err(self.syntax_error(format!("unknown declaration {}", keyword)))
+        }
+    }
+
+    // the text of the next token, which may be quoted but not punctuation
+    fn read_name(&mut self) -> Result<String, Error>
+    {
+        if let Some(token) = self.tokens_iter.front().filter(|token| Grammar::is_punctuation(token))
+        {
+            return Err(self.syntax_error(format!("expected a symbol, found {}", token.text)));
+        }
+        self.next()
+    }
+
+    fn read_symbol(&mut self) -> Result<Symbol, Error>
+    {
+        let symbol = self.tokens_iter.front().map(|token| self.grammar.classify(token));
+        self.read_name()?;
+        Ok(symbol.unwrap())
+    }
+
+    fn parse_rule(&mut self) -> Result<(Symbol, Vec<Alternative>), Error>
+    {
+        if let Some(token) = self.tokens_iter.front().filter(|token| !Grammar::is_punctuation(token) && self.grammar.classify(token).terminal)
+        {
+            return Err(self.syntax_error(format!("expected a non-terminal, found {}", token.text)));
+        }
+        let lhs = self.read_symbol()?;
+
+        self.expect("->")?;
+        let mut prod_list = Vec::<Alternative>::new();
+        prod_list.push((self.parse_rhs()?, self.parse_prec()?));
+
+        while self.next_symbol_is("|")
+        {
+            self.expect("|")?;
+
+            prod_list.push((self.parse_rhs()?, self.parse_prec()?));
+        }
+        self.expect(";")?;
+
+        Ok((lhs, prod_list))
+    }
+
+    fn parse_rhs(&mut self) -> Result<Vec<Symbol>, Error>
+    {
+        let mut out = Vec::<Symbol>::new();
+
+        while !self.next_symbol_is(";") && !self.next_symbol_is("|") && !self.next_symbol_is(")") && !self.next_symbol_is("%prec")
+        {
+            out.push(self.parse_item()?);
+        }
+
+        Ok(out)
+    }
+
+    // an optional %prec name at the end of an alternative, naming a terminal with declared precedence
+    fn parse_prec(&mut self) -> Result<Option<Symbol>, Error>
+    {
+        if !self.next_symbol_is("%prec")
+        {
+            return Ok(None);
+        }
+        self.next()?;
+
+        let symbol = self.tokens_iter.front().map(|token| Symbol { label: token.text.clone(), terminal: true });
+        if let Some(symbol) = symbol.filter(|symbol| !self.grammar.precedence.contains_key(symbol))
+        {
+            return Err(self.syntax_error(format!("{} has no declared precedence", symbol.label)));
+        }
+        let name = self.read_name()?;
+
+        Ok(Some(Symbol { label: name, terminal: true }))
+    }
+
+    // a symbol or a parenthesized group, followed by any number of *, + and ?
+    fn parse_item(&mut self) -> Result<Symbol, Error>
+    {
+        let mut symbol = if self.next_symbol_is("(")
+        {
+            self.expect("(")?;
+            let mut alternatives = vec![self.parse_rhs()?];
+            while self.next_symbol_is("|")
+            {
+                self.expect("|")?;
+                alternatives.push(self.parse_rhs()?);
+            }
+            self.expect(")")?;
+
+            if alternatives.len() == 1 && alternatives[0].len() == 1
+            {
+                alternatives.pop().unwrap().pop().unwrap()
+            }
+            else
+            {
+                let label = format!("({})", alternatives
+                    .iter()
+                    .map(|rhs| rhs.iter().map(|symbol| self.grammar.source_text(symbol)).collect::<Vec<String>>().join(" "))
+                    .collect::<Vec<String>>()
+                    .join(" | "));
+                self.grammar.add_helper(label, Helper::Group, alternatives)
             }
         }
-        out
+        else
+        {
+            self.read_symbol()?
+        };
+
+        loop
+        {
+            let (operator, helper) = if self.next_symbol_is("*")
+            {
+                ("*", Helper::Repeat { element: symbol.clone(), at_least_one: false })
+            }
+            else if self.next_symbol_is("+")
+            {
+                ("+", Helper::Repeat { element: symbol.clone(), at_least_one: true })
+            }
+            else if self.next_symbol_is("?")
+            {
+                ("?", Helper::Optional { element: symbol.clone() })
+            }
+            else
+            {
+                return Ok(symbol);
+            };
+            self.next()?;
+
+            let label = format!("{}{}", self.grammar.source_text(&symbol), operator);
+            let productions = Grammar::helper_productions(&Symbol { label: label.clone(), terminal: false }, &helper, None);
+            symbol = self.grammar.add_helper(label, helper, productions);
+        }
+    }
+
+    // punctuation only; a quoted '|' is a terminal
+    fn next_symbol_is(&self, expected: &str) -> bool
+    {
+        self.tokens_iter.front().is_some_and(|token| !token.quoted && token.text == expected)
+    }
+
+    fn next(&mut self) -> Result<String, Error> 
+    {
+        match self.tokens_iter.front()
+        {
+            Some(_) => Ok(self.tokens_iter.pop_front().unwrap().text),
+            None => Err(self.syntax_error(String::from("unexpected end of file")))
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), Error>
+    {
+        if self.next_symbol_is(expected)
+        {
+            self.next()?;
+            Ok(())
+        }
+        else
+        {
+            let found = self.tokens_iter.front().map(|token| token.text.clone()).unwrap_or_else(|| String::from("end of file"));
+            Err(self.syntax_error(format!("expected {}, found {}", expected, found)))
+        }
+    }
+
+    // an error located at the next unread token
+    fn syntax_error(&self, message: String) -> Error
+    {
+        let (line, column) = self.tokens_iter
+            .front()
+            .map(|token| (token.line, token.column))
+            .unwrap_or(self.end);
+        Error::Syntax
+        {
+            line,
+            column,
+            message
+        }
+    }
+}
+
+#[test]
+fn test_undefined_nonterminal()
+{
+    let error = Grammar::from_file("data/undefined").err().unwrap();
+    assert_eq!(error, Error::UndefinedNonterminal(Symbol::from(String::from("T"))));
+}
+
+#[test]
+fn test_syntax_error_position()
+{
+    match Grammar::from_file("data/bad_syntax").err().unwrap()
+    {
+        Error::Syntax { line, column, message } => {
+            assert_eq!((line, column), (3, 9));
+            assert_eq!(message, "expected a symbol, found ->");
+        },
+        error => panic!("unexpected error {}", error)
+    }
+    assert!(matches!(Grammar::from_file("data/missing"), Err(Error::Io { .. })));
+}
+
+#[test]
+fn test_declarations_and_literals()
+{
+    let grammar = Grammar::from_file("data/calc").unwrap();
+
+    assert_eq!(grammar.start.label, "Program");
+    for label in &["+", "*", "(", ")", "$", "NUM"]
+    {
+        assert!(grammar.terminals.contains(&Symbol { label: label.to_string(), terminal: true }), "{}", label);
+    }
+    assert_eq!(grammar.nonterminals.len(), 4);
+    assert_eq!(grammar.productions.len(), 4);
+}
+
+#[test]
+fn test_format_errors()
+{
+    let error = |text: &str| {
+        match Grammar::from_str(text)
+        {
+            Err(Error::Syntax { message, .. }) => message,
+            result => panic!("unexpected {:?}", result)
+        }
+    };
+
+    assert_eq!(error("S -> 'a ;"), "unterminated quoted terminal");
+    assert_eq!(error("S -> a ; %token b ;"), "declarations must come before the rules");
+    assert_eq!(error("%union a ;"), "unknown declaration %union");
+    assert_eq!(error("S -> a %prec b ;"), "b has no declared precedence");
+    assert_eq!(error("%token A ; A -> b ;"), "expected a non-terminal, found A");
+    assert_eq!(error("S -> a # no terminator ;"), "unexpected end of file");
+}
+
+#[test]
+fn test_ebnf_helpers()
+{
+    let grammar = Grammar::from_file("data/ebnf").unwrap();
+    let helper = |label: &str| Symbol { label: label.to_string(), terminal: false };
+    let item = helper("Item");
+
+    assert_eq!(grammar.helpers.len(), 5);
+    assert_eq!(grammar.helpers[&helper("(, Item)")], Helper::Group);
+    assert_eq!(grammar.helpers[&helper("(, Item)*")], Helper::Repeat { element: helper("(, Item)"), at_least_one: false });
+    assert_eq!(grammar.helpers[&helper("(Item (, Item)*)?")], Helper::Optional { element: helper("(Item (, Item)*)") });
+    assert_eq!(grammar.get_rhs(&helper("(, Item)*"), 0).unwrap(), &vec![helper("(, Item)*"), helper("(, Item)")]);
+    assert_eq!(grammar.get_rhs(&helper("(Item (, Item)*)"), 0).unwrap(), &vec![item.clone(), helper("(, Item)*")]);
+    assert!(grammar.lambda_deriving_symbols().contains(&helper("(Item (, Item)*)?")));
+    assert!(!grammar.lambda_deriving_symbols().contains(&helper("num+")));
+
+    // X+ becomes X X* when recursing to the right
+    let right = grammar.right_recursive();
+    let num = Symbol { label: String::from("num"), terminal: true };
+    assert_eq!(right.productions[&helper("num+")], vec![vec![num.clone(), helper("num*")]]);
+    assert_eq!(right.productions[&helper("num*")], vec![vec![num, helper("num*")], vec![]]);
+    assert!(right.lambda_deriving_symbols().contains(&helper("num*")));
+
+    // operators must be quoted to be terminals
+    let grammar = Grammar::from_str("S -> '(' S* ')' '+'? ;").unwrap();
+    assert_eq!(grammar.get_rhs(&helper("S"), 0).unwrap()[2].label, ")");
+    assert!(grammar.helpers.contains_key(&helper("'+'?")));
+    match Grammar::from_str("S -> ( a ;")
+    {
+        Err(Error::Syntax { message, .. }) => assert_eq!(message, "expected ), found ;"),
+        result => panic!("unexpected {:?}", result)
     }
+}
 
+#[test]
+fn test_first_and_follow_k()
+{
+    let grammar = Grammar::from_str("S -> A b A ; A -> a A | ;").unwrap();
+    let strings = |list: &[&str]| list
+        .iter()
+        .map(|string| string.split_whitespace().map(|label| Symbol::from(label.to_string())).collect::<Vec<Symbol>>())
+        .collect::<HashSet<Vec<Symbol>>>();
+    let a = Symbol::from(String::from("A"));
 
-}
\ No newline at end of file
+    let first = grammar.first_k(2);
+    assert_eq!(first[&a], strings(&["", "a", "a a"]));
+    assert_eq!(first[&grammar.start], strings(&["b", "b a", "a b", "a a"]));
+
+    // after the second A the input ends
+    let follow = grammar.follow_k(2, &first);
+    assert_eq!(follow[&a], strings(&["", "b", "b a"]));
+}
+
+#[test]
+fn test_cached_sets()
+{
+    let grammar = Grammar::from_str("S -> A c ; A -> B a | ; B -> A b | d ;").unwrap();
+    let set = |labels: &str| labels.split_whitespace().map(|label| Symbol::from(label.to_string())).collect::<HashSet<Symbol>>();
+    let symbol = |label: &str| Symbol::from(label.to_string());
+
+    assert_eq!(grammar.lambda_deriving_symbols(), &set("A"));
+    assert_eq!(grammar.first_sets()[&symbol("A")], set("b d"));
+    assert_eq!(grammar.first_sets()[&symbol("B")], set("b d"));
+    assert_eq!(grammar.first_of_symbol(&symbol("S")), set("b c d"));
+    assert_eq!(grammar.follow_sets()[&symbol("A")], set("b c"));
+    assert_eq!(grammar.follow(&symbol("B")), set("a"));
+    assert_eq!(grammar.follow(&symbol("a")), set("b c"));
+    assert_eq!(grammar.first_of_rhs(&[symbol("A"), symbol("c")]), set("b c d"));
+}
@@ -0,0 +1,760 @@
+use std::collections::{BTreeSet, HashMap};
+use crate::symbol::Symbol;
+use crate::token::{Span, Token};
+use crate::error::Error;
+
+// A named regular expression. Longest match wins; equal lengths go to the highest priority, then the earliest rule.
+#[derive(Debug, Clone)]
+pub struct TokenRule
+{
+    pub name: String,
+    pub pattern: String,
+    pub priority: i32,
+    // skipped tokens (whitespace, comments) are matched but never handed to the parser
+    pub skip: bool
+}
+
+impl TokenRule
+{
+    pub fn token(name: &str, pattern: &str) -> TokenRule
+    {
+        TokenRule
+        {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            priority: 0,
+            skip: false
+        }
+    }
+
+    pub fn skip(name: &str, pattern: &str) -> TokenRule
+    {
+        TokenRule
+        {
+            skip: true,
+            ..TokenRule::token(name, pattern)
+        }
+    }
+
+    pub fn with_priority(self, priority: i32) -> TokenRule
+    {
+        TokenRule
+        {
+            priority,
+            ..self
+        }
+    }
+}
+
+// inclusive ranges of code points
+type CharClass = Vec<(u32, u32)>;
+
+#[derive(Debug, Clone)]
+enum Regex
+{
+    Empty,
+    Class(CharClass),
+    Concat(Vec<Regex>),
+    Alternative(Vec<Regex>),
+    Star(Box<Regex>),
+    Plus(Box<Regex>),
+    Optional(Box<Regex>)
+}
+
+struct RegexParser<'a>
+{
+    rule: &'a str,
+    chars: Vec<char>,
+    position: usize
+}
+
+impl RegexParser<'_>
+{
+    fn parse(rule: &str, pattern: &str) -> Result<Regex, Error>
+    {
+        let mut parser = RegexParser
+        {
+            rule,
+            chars: pattern.chars().collect(),
+            position: 0
+        };
+
+        let regex = parser.parse_alternative()?;
+        if parser.position < parser.chars.len()
+        {
+            return Err(parser.error(format!("unexpected {}", parser.chars[parser.position])));
+        }
+        Ok(regex)
+    }
+
+    fn error(&self, message: String) -> Error
+    {
+        Error::Regex
+        {
+            rule: self.rule.to_string(),
+            position: self.position,
+            message
+        }
+    }
+
+    fn peek(&self) -> Option<char>
+    {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn next(&mut self) -> Result<char, Error>
+    {
+        let c = self.peek().ok_or_else(|| self.error(String::from("unexpected end of pattern")))?;
+        self.position += 1;
+        Ok(c)
+    }
+
+    fn parse_alternative(&mut self) -> Result<Regex, Error>
+    {
+        let mut alternatives = vec![self.parse_concat()?];
+        while self.peek() == Some('|')
+        {
+            self.position += 1;
+            alternatives.push(self.parse_concat()?);
+        }
+
+        if alternatives.len() == 1
+        {
+            Ok(alternatives.pop().unwrap())
+        }
+        else
+        {
+            Ok(Regex::Alternative(alternatives))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Regex, Error>
+    {
+        let mut parts = Vec::<Regex>::new();
+        while let Some(c) = self.peek()
+        {
+            if c == '|' || c == ')'
+            {
+                break;
+            }
+            parts.push(self.parse_repeat()?);
+        }
+
+        match parts.len()
+        {
+            0 => Ok(Regex::Empty),
+            1 => Ok(parts.pop().unwrap()),
+            _ => Ok(Regex::Concat(parts))
+        }
+    }
+
+    fn parse_repeat(&mut self) -> Result<Regex, Error>
+    {
+        let mut regex = self.parse_atom()?;
+        loop
+        {
+            regex = match self.peek()
+            {
+                Some('*') => Regex::Star(Box::new(regex)),
+                Some('+') => Regex::Plus(Box::new(regex)),
+                Some('?') => Regex::Optional(Box::new(regex)),
+                _ => return Ok(regex)
+            };
+            self.position += 1;
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Regex, Error>
+    {
+        match self.next()?
+        {
+            '(' => {
+                let regex = self.parse_alternative()?;
+                if self.peek() != Some(')')
+                {
+                    return Err(self.error(String::from("expected )")));
+                }
+                self.position += 1;
+                Ok(regex)
+            },
+            '[' => self.parse_class(),
+            '.' => Ok(Regex::Class(negate(&vec![('\n' as u32, '\n' as u32)]))),
+            '\\' => Ok(Regex::Class(self.parse_escape()?)),
+            c @ ('*' | '+' | '?') => Err(self.error(format!("nothing to repeat before {}", c))),
+            c => Ok(Regex::Class(vec![(c as u32, c as u32)]))
+        }
+    }
+
+    // after a backslash
+    fn parse_escape(&mut self) -> Result<CharClass, Error>
+    {
+        let single = |c: char| vec![(c as u32, c as u32)];
+        Ok(match self.next()?
+        {
+            'd' => vec![('0' as u32, '9' as u32)],
+            'w' => vec![('0' as u32, '9' as u32), ('A' as u32, 'Z' as u32), ('_' as u32, '_' as u32), ('a' as u32, 'z' as u32)],
+            's' => vec![('\t' as u32, '\r' as u32), (' ' as u32, ' ' as u32)],
+            'n' => single('\n'),
+            't' => single('\t'),
+            'r' => single('\r'),
+            c => single(c)
+        })
+    }
+
+    // after an opening bracket
+    fn parse_class(&mut self) -> Result<Regex, Error>
+    {
+        let negated = self.peek() == Some('^');
+        if negated
+        {
+            self.position += 1;
+        }
+
+        let mut class = CharClass::new();
+        let mut first = true;
+        loop
+        {
+            let c = self.next()?;
+            if c == ']' && !first
+            {
+                break;
+            }
+            first = false;
+
+            let low = if c == '\\'
+            {
+                let escaped = self.parse_escape()?;
+                if escaped.len() > 1 || escaped[0].0 != escaped[0].1
+                {
+                    class.extend(escaped);
+                    continue;
+                }
+                escaped[0].0
+            }
+            else
+            {
+                c as u32
+            };
+
+            if self.peek() == Some('-') && self.chars.get(self.position + 1).is_some_and(|c| *c != ']')
+            {
+                self.position += 1;
+                let high = match self.next()?
+                {
+                    '\\' => self.parse_escape()?[0].0,
+                    c => c as u32
+                };
+                if high < low
+                {
+                    return Err(self.error(String::from("character range out of order")));
+                }
+                class.push((low, high));
+            }
+            else
+            {
+                class.push((low, low));
+            }
+        }
+
+        if negated
+        {
+            class = negate(&class);
+        }
+        Ok(Regex::Class(class))
+    }
+}
+
+// every code point not in the class
+fn negate(class: &CharClass) -> CharClass
+{
+    let mut sorted = class.clone();
+    sorted.sort();
+
+    let mut out = CharClass::new();
+    let mut next = 0;
+    for (low, high) in sorted
+    {
+        if low > next
+        {
+            out.push((next, low - 1));
+        }
+        next = next.max(high + 1);
+    }
+    if next <= char::MAX as u32
+    {
+        out.push((next, char::MAX as u32));
+    }
+    out
+}
+
+#[derive(Debug, Default)]
+struct NfaState
+{
+    epsilon: Vec<usize>,
+    transitions: Vec<(CharClass, usize)>,
+    accept: Option<usize>
+}
+
+struct Nfa
+{
+    states: Vec<NfaState>
+}
+
+impl Nfa
+{
+    fn add_state(&mut self) -> usize
+    {
+        self.states.push(NfaState::default());
+        self.states.len() - 1
+    }
+
+    // Thompson construction; returns the fragment's start and end states
+    fn build(&mut self, regex: &Regex) -> (usize, usize)
+    {
+        let start = self.add_state();
+        let end = self.add_state();
+        match regex
+        {
+            Regex::Empty => {
+                self.states[start].epsilon.push(end);
+            },
+            Regex::Class(class) => {
+                self.states[start].transitions.push((class.clone(), end));
+            },
+            Regex::Concat(parts) => {
+                let mut previous = start;
+                for part in parts
+                {
+                    let (part_start, part_end) = self.build(part);
+                    self.states[previous].epsilon.push(part_start);
+                    previous = part_end;
+                }
+                self.states[previous].epsilon.push(end);
+            },
+            Regex::Alternative(alternatives) => {
+                for alternative in alternatives
+                {
+                    let (alternative_start, alternative_end) = self.build(alternative);
+                    self.states[start].epsilon.push(alternative_start);
+                    self.states[alternative_end].epsilon.push(end);
+                }
+            },
+            Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => {
+                let (inner_start, inner_end) = self.build(inner);
+                self.states[start].epsilon.push(inner_start);
+                self.states[inner_end].epsilon.push(end);
+                if !matches!(regex, Regex::Plus(_))
+                {
+                    self.states[start].epsilon.push(end);
+                }
+                if !matches!(regex, Regex::Optional(_))
+                {
+                    self.states[inner_end].epsilon.push(inner_start);
+                }
+            }
+        }
+        (start, end)
+    }
+
+    fn epsilon_closure(&self, states: &mut BTreeSet<usize>)
+    {
+        let mut work_list = states.iter().cloned().collect::<Vec<usize>>();
+        while let Some(state) = work_list.pop()
+        {
+            for next in &self.states[state].epsilon
+            {
+                if states.insert(*next)
+                {
+                    work_list.push(*next);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DfaState
+{
+    // next state per input interval
+    transitions: Vec<Option<usize>>,
+    // index of the winning rule
+    accept: Option<usize>
+}
+
+pub struct Lexer
+{
+    rules: Vec<TokenRule>,
+    // sorted first code points of the intervals the input alphabet is split into
+    boundaries: Vec<u32>,
+    states: Vec<DfaState>,
+    start: usize,
+    // the terminal appended after the last token, if any
+    end_marker: Option<String>
+}
+
+impl Lexer
+{
+    pub fn new(rules: Vec<TokenRule>) -> Result<Lexer, Error>
+    {
+        let mut nfa = Nfa
+        {
+            states: vec![]
+        };
+        let nfa_start = nfa.add_state();
+        for (index, rule) in rules.iter().enumerate()
+        {
+            // the parsers give the error terminal and the empty label meanings of their own
+            let reserved = match rule.name.as_str()
+            {
+                "" => Some("the name is empty"),
+                name if name == Symbol::error().label => Some("the name is reserved for error recovery"),
+                _ => None
+            };
+            if let Some(message) = reserved
+            {
+                return Err(Error::Regex
+                {
+                    rule: rule.name.clone(),
+                    position: 0,
+                    message: message.to_string()
+                });
+            }
+            let regex = RegexParser::parse(&rule.name, &rule.pattern)?;
+            let (start, end) = nfa.build(&regex);
+            nfa.states[nfa_start].epsilon.push(start);
+            nfa.states[end].accept = Some(index);
+        }
+
+        let boundaries = Lexer::split_alphabet(&nfa);
+        let (states, start) = Lexer::build_dfa(&nfa, nfa_start, &boundaries, &rules);
+        let (states, start) = Lexer::minimize(states, start);
+
+        Ok(Lexer
+        {
+            rules,
+            boundaries,
+            states,
+            start,
+            end_marker: Some(String::from("$"))
+        })
+    }
+
+    // split the code points into intervals that no character class divides
+    fn split_alphabet(nfa: &Nfa) -> Vec<u32>
+    {
+        let mut boundaries = vec![0];
+        for state in &nfa.states
+        {
+            for (class, _) in &state.transitions
+            {
+                for (low, high) in class
+                {
+                    boundaries.push(*low);
+                    boundaries.push(high + 1);
+                }
+            }
+        }
+        boundaries.retain(|boundary| *boundary <= char::MAX as u32);
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        boundaries
+    }
+
+    fn interval(boundaries: &[u32], c: u32) -> usize
+    {
+        boundaries.partition_point(|boundary| *boundary <= c) - 1
+    }
+
+    // subset construction
+    fn build_dfa(nfa: &Nfa, nfa_start: usize, boundaries: &[u32], rules: &[TokenRule]) -> (Vec<DfaState>, usize)
+    {
+        // the intervals covered by each class, so classes are only searched once
+        let transitions = nfa.states
+            .iter()
+            .map(|state| state.transitions
+                .iter()
+                .map(|(class, target)| {
+                    let intervals = class
+                        .iter()
+                        .flat_map(|(low, high)| Lexer::interval(boundaries, *low)..=Lexer::interval(boundaries, *high))
+                        .collect::<Vec<usize>>();
+                    (intervals, *target)
+                })
+                .collect::<Vec<(Vec<usize>, usize)>>())
+            .collect::<Vec<Vec<(Vec<usize>, usize)>>>();
+
+        let mut start_set = BTreeSet::<usize>::new();
+        start_set.insert(nfa_start);
+        nfa.epsilon_closure(&mut start_set);
+
+        let mut ids = HashMap::<BTreeSet<usize>, usize>::new();
+        let mut sets = vec![start_set.clone()];
+        let mut states = Vec::<DfaState>::new();
+        ids.insert(start_set, 0);
+
+        let mut index = 0;
+        while index < sets.len()
+        {
+            let mut moves = HashMap::<usize, BTreeSet<usize>>::new();
+            for nfa_state in &sets[index]
+            {
+                for (intervals, target) in &transitions[*nfa_state]
+                {
+                    for interval in intervals
+                    {
+                        moves.entry(*interval).or_default().insert(*target);
+                    }
+                }
+            }
+
+            let mut state = DfaState
+            {
+                transitions: vec![None; boundaries.len()],
+                accept: sets[index]
+                    .iter()
+                    .filter_map(|nfa_state| nfa.states[*nfa_state].accept)
+                    .min_by_key(|rule| (-rules[*rule].priority, *rule))
+            };
+
+            for (interval, mut set) in moves
+            {
+                nfa.epsilon_closure(&mut set);
+                let id = match ids.get(&set)
+                {
+                    Some(id) => *id,
+                    None => {
+                        sets.push(set.clone());
+                        ids.insert(set, sets.len() - 1);
+                        sets.len() - 1
+                    }
+                };
+                state.transitions[interval] = Some(id);
+            }
+
+            states.push(state);
+            index += 1;
+        }
+
+        (states, 0)
+    }
+
+    // Moore's partition refinement, starting from the states grouped by accepted rule
+    fn minimize(states: Vec<DfaState>, start: usize) -> (Vec<DfaState>, usize)
+    {
+        let mut block = Vec::<usize>::new();
+        let mut accept_blocks = HashMap::<Option<usize>, usize>::new();
+        for state in &states
+        {
+            let next_id = accept_blocks.len();
+            block.push(*accept_blocks.entry(state.accept).or_insert(next_id));
+        }
+        let mut block_count = accept_blocks.len();
+
+        loop
+        {
+            let mut signatures = HashMap::<(usize, Vec<Option<usize>>), usize>::new();
+            let new_block = states
+                .iter()
+                .enumerate()
+                .map(|(id, state)| {
+                    let signature = (block[id], state.transitions.iter().map(|next| next.map(|next| block[next])).collect());
+                    let next_id = signatures.len();
+                    *signatures.entry(signature).or_insert(next_id)
+                })
+                .collect::<Vec<usize>>();
+
+            block = new_block;
+            if signatures.len() == block_count
+            {
+                break;
+            }
+            block_count = signatures.len();
+        }
+
+        let mut minimized = vec![None; block_count];
+        for (id, state) in states.iter().enumerate()
+        {
+            if minimized[block[id]].is_none()
+            {
+                minimized[block[id]] = Some(DfaState
+                {
+                    transitions: state.transitions.iter().map(|next| next.map(|next| block[next])).collect(),
+                    accept: state.accept
+                });
+            }
+        }
+
+        (minimized.into_iter().map(Option::unwrap).collect(), block[start])
+    }
+
+    // the end marker tokenize appends, $ unless changed; None for grammars without one
+    pub fn with_end_marker(mut self, end_marker: Option<&str>) -> Lexer
+    {
+        self.end_marker = end_marker.map(String::from);
+        self
+    }
+
+    pub fn state_count(&self) -> usize
+    {
+        self.states.len()
+    }
+
+    // Splits the input into tokens by longest match, followed by the end marker, $ by default.
+    pub fn tokenize(&self, input: &str) -> Result<Vec<Token>, Error>
+    {
+        let mut tokens = Vec::<Token>::new();
+        let mut position = 0;
+        let mut span = Span::origin();
+
+        while position < input.len()
+        {
+            let mut state = self.start;
+            let mut last_accept: Option<(usize, usize)> = None;
+
+            for (offset, c) in input[position..].char_indices()
+            {
+                match self.states[state].transitions[Lexer::interval(&self.boundaries, c as u32)]
+                {
+                    Some(next) => state = next,
+                    None => break
+                }
+                if let Some(rule) = self.states[state].accept
+                {
+                    last_accept = Some((rule, position + offset + c.len_utf8()));
+                }
+            }
+
+            let (rule, end) = last_accept.ok_or_else(|| Error::Lex
+                {
+                    span: span.over(&input[position..position + input[position..].chars().next().unwrap().len_utf8()]),
+                    message: format!("unexpected character {:?}", input[position..].chars().next().unwrap())
+                })?;
+
+            let lexeme = &input[position..end];
+            if !self.rules[rule].skip
+            {
+                tokens.push(Token
+                {
+                    symbol: Symbol
+                    {
+                        label: self.rules[rule].name.clone(),
+                        terminal: true
+                    },
+                    lexeme: lexeme.to_string(),
+                    span: span.over(lexeme)
+                });
+            }
+            span = span.after(lexeme);
+            position = end;
+        }
+
+        if let Some(end_marker) = &self.end_marker
+        {
+            tokens.push(Token
+            {
+                symbol: Symbol
+                {
+                    label: end_marker.clone(),
+                    terminal: true
+                },
+                lexeme: String::new(),
+                span
+            });
+        }
+
+        Ok(tokens)
+    }
+}
+
+#[cfg(test)]
+fn labels(tokens: &[Token]) -> Vec<String>
+{
+    tokens.iter().map(|token| format!("{}:{}", token.symbol, token.lexeme)).collect()
+}
+
+#[test]
+fn test_tokenize()
+{
+    let lexer = Lexer::new(vec![
+        TokenRule::token("id", "[a-zA-Z_]\\w*"),
+        TokenRule::token("if", "if").with_priority(1),
+        TokenRule::token("num", "\\d+(\\.\\d+)?"),
+        TokenRule::token("assign", "="),
+        TokenRule::token("eq", "=="),
+        TokenRule::token("semi", ";"),
+        TokenRule::skip("space", "[ \\t\\n]+"),
+        TokenRule::skip("comment", "//[^\\n]*")
+    ]).unwrap();
+
+    let tokens = lexer.tokenize("if x==1.5; // done\niffy=2;").unwrap();
+    assert_eq!(labels(&tokens), vec![
+        "if:if", "id:x", "eq:==", "num:1.5", "semi:;",
+        "id:iffy", "assign:=", "num:2", "semi:;", "$:"
+    ]);
+    assert_eq!(tokens[5].span, Span { start: 19, end: 23, line: 2, column: 1 });
+
+    match lexer.tokenize("x =\n  @")
+    {
+        Err(Error::Lex { span, .. }) => assert_eq!(span, Span { start: 6, end: 7, line: 2, column: 3 }),
+        result => panic!("unexpected {:?}", result)
+    }
+}
+
+#[test]
+fn test_minimization()
+{
+    // the textbook example: the minimal DFA for (a|b)*abb has 4 states
+    let lexer = Lexer::new(vec![TokenRule::token("x", "(a|b)*abb")]).unwrap();
+    assert_eq!(lexer.state_count(), 4);
+
+    assert_eq!(labels(&lexer.tokenize("ababbabb").unwrap()), vec!["x:ababbabb", "$:"]);
+    assert!(lexer.tokenize("abab").is_err());
+
+    // the end marker is the grammar's to choose, or left out
+    let lexer = lexer.with_end_marker(Some("eof"));
+    assert_eq!(labels(&lexer.tokenize("abb").unwrap()), vec!["x:abb", "eof:"]);
+    let lexer = lexer.with_end_marker(None);
+    assert_eq!(labels(&lexer.tokenize("abb").unwrap()), vec!["x:abb"]);
+}
+
+#[test]
+fn test_bad_pattern()
+{
+    match Lexer::new(vec![TokenRule::token("broken", "a(b")])
+    {
+        Err(Error::Regex { rule, .. }) => assert_eq!(rule, "broken"),
+        _ => panic!("expected a regex error")
+    }
+
+    // names the parsers reserve
+    for name in &["error", ""]
+    {
+        match Lexer::new(vec![TokenRule::token("x", "x"), TokenRule::token(name, "y")])
+        {
+            Err(Error::Regex { rule, position: 0, .. }) => assert_eq!(rule, *name),
+            _ => panic!("expected {:?} to be refused", name)
+        }
+    }
+}
+
+#[test]
+fn test_feeds_parsers()
+{
+    use crate::grammar::Grammar;
+    use crate::ll_parser::LLParser;
+    use crate::lr_parser::{LRParser, Mode};
+
+    let lexer = Lexer::new(vec![
+        TokenRule::token("plus", "\\+"),
+        TokenRule::token("num", "[0-9]+"),
+        TokenRule::skip("space", "\\s+")
+    ]).unwrap();
+    let grammar = Grammar::from_file("data/eeeee").unwrap();
+
+    let tokens = lexer.tokenize("++1 22\n3").unwrap();
+    let ll_tree = LLParser::new(grammar.clone()).unwrap().parse_tokens(tokens.clone()).unwrap();
+    let lr_tree = LRParser::new(grammar, Mode::LALR).unwrap().parse_tokens(tokens).unwrap();
+
+    assert_eq!(ll_tree, lr_tree);
+    assert_eq!(lr_tree.lexemes(), vec!["+", "+", "1", "22", "3", ""]);
+}
@@ -1,4 +1,16 @@
 pub mod grammar;
+pub mod builder;
 pub mod symbol;
 pub mod ll_parser;
 pub mod lr_parser;
+pub mod glr_parser;
+pub mod parse_tree;
+pub mod actions;
+pub mod error;
+pub mod token;
+pub mod lexer;
+pub mod transform;
+pub mod analysis;
+pub mod writer;
+pub mod tables;
+pub mod codegen;
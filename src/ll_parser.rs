@@ -1,50 +1,122 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::symbol::Symbol;
 use crate::grammar::Grammar;
+use crate::parse_tree::{ParseTree, TreeBuilder};
+use crate::actions::{self, Actions, Slot};
+use crate::error::{Conflict, Error, Recovered};
+use crate::token::{Span, Token};
+
+// a predicted nonterminal whose children are still being parsed
+struct PartialNode<T>
+{
+    symbol: Symbol,
+    rhs_id: u32,
+    arity: usize,
+    children: Vec<(Slot<T>, Span)>,
+    // covers the children attached so far
+    span: Option<Span>
+}
+
+// what LLParser::parse_recovering does after a parse error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery
+{
+    // stop at the first error
+    Abort,
+    // insert a missing terminal, skip tokens until an expected nonterminal can go on or one of its
+    // FOLLOW set comes, or one starting a symbol below it on the stack, and give up on it in the latter case
+    PanicMode,
+    // delete or insert a single token where the next few then parse, falling back to panic mode
+    PhraseLevel
+}
 
 pub struct LLParser
 {
-    grammar: Grammar,
-    // (LHS, next_token) -> rhs_id
-    parse_table: HashMap<(Symbol, Symbol), u32> 
+    pub(crate) grammar: Grammar,
+    // (LHS, up to k next tokens, fewer where the input ends) -> rhs_id
+    pub(crate) parse_table: HashMap<(Symbol, Vec<Symbol>), u32>,
+    pub(crate) k: usize,
+    // whether parsing prints the stack and remaining input at every step
+    pub(crate) trace: bool
 }
 
 impl LLParser
 {
-    pub fn new(grammar: Grammar) -> LLParser
+    // the most lookahead tried when looking for the k that would resolve a conflict
+    pub const MAX_LOOKAHEAD: usize = 4;
+    // how many tokens past a phrase-level repair have to parse for it to be made
+    const REPAIR_TOKENS: usize = 3;
+
+    pub fn new(grammar: Grammar) -> Result<LLParser, Error>
     {
-        
-        let parse_table = LLParser::build_parse_table(&grammar);
+        LLParser::with_lookahead(grammar, 1)
+    }
 
-        LLParser{
-            grammar,
-            parse_table
+    // a strong LL(k) parser; repetitions are parsed right recursive, whatever the grammar was built with
+    pub fn with_lookahead(grammar: Grammar, k: usize) -> Result<LLParser, Error>
+    {
+        LLParser::build(grammar, k, false)
+    }
+
+    // for debugging: prints the stack and the remaining input at every step of a parse
+    pub fn traced(grammar: Grammar, k: usize) -> Result<LLParser, Error>
+    {
+        LLParser::build(grammar, k, true)
+    }
+
+    fn build(grammar: Grammar, k: usize, trace: bool) -> Result<LLParser, Error>
+    {
+        if k == 0
+        {
+            return Err(Error::Lookahead(k));
         }
+        let grammar = grammar.right_recursive();
+        let parse_table = LLParser::build_parse_table(&grammar, k)?;
+
+        Ok(LLParser{
+            grammar,
+            parse_table,
+            k,
+            trace
+        })
     }
 
-    fn build_parse_table(grammar: &Grammar) -> HashMap<(Symbol, Symbol), u32>
+    // FIRST_k(rhs FOLLOW_k(lhs)) for every production, by lhs and rhs_id
+    fn select_sets(grammar: &Grammar, k: usize) -> HashMap<Symbol, Vec<HashSet<Vec<Symbol>>>>
     {
-        let mut out = HashMap::<(Symbol, Symbol), u32>::new();
+        let first = grammar.first_k(k);
+        let follow = grammar.follow_k(k, &first);
 
-        for (lhs, prod_list) in &grammar.productions
+        grammar.productions
+            .iter()
+            .map(|(lhs, prod_list)| (lhs.clone(), prod_list
+                .iter()
+                .map(|rhs| Grammar::concat_k(&Grammar::first_k_of_rhs(rhs, k, &first), &follow[lhs], k))
+                .collect()))
+            .collect()
+    }
+
+    fn build_parse_table(grammar: &Grammar, k: usize) -> Result<HashMap<(Symbol, Vec<Symbol>), u32>, Error>
+    {
+        let mut out = HashMap::<(Symbol, Vec<Symbol>), u32>::new();
+        let mut conflicts = Vec::<Conflict>::new();
+
+        for (lhs, select_sets) in LLParser::select_sets(grammar, k)
         {
-            for (rhs_id, production) in prod_list.iter().enumerate()
+            for (rhs_id, select_set) in select_sets.into_iter().enumerate()
             {
-                let mut select_set = grammar.first_of_rhs(production);
-                if grammar.rhs_derives_lambda(&production)
-                {
-                    for symbol in grammar.follow(lhs)
-                    {
-                        select_set.insert(symbol);
-                    }
-                }
-
                 for item in select_set
                 {
                     let key = (lhs.clone(), item.clone());
-                    if out.contains_key(&key)
+                    if let Some(other_rhs_id) = out.get(&key)
                     {
-                        panic!("Predict set conflict for non-terminal {} with next symbol {}.", lhs, item);
+                        conflicts.push(Conflict::Predict
+                        {
+                            lhs: lhs.clone(),
+                            lookahead: item,
+                            productions: (*other_rhs_id, rhs_id as u32),
+                            resolved_at: None
+                        });
                     }
                     else
                     {
@@ -57,80 +129,529 @@ impl LLParser
 
         }
 
-        out
+        if conflicts.is_empty()
+        {
+            return Ok(out);
+        }
+
+        // the first larger k that separates every production of the conflicting nonterminal
+        for larger in k + 1..=LLParser::MAX_LOOKAHEAD
+        {
+            let select_sets = LLParser::select_sets(grammar, larger);
+            for conflict in conflicts.iter_mut()
+            {
+                if let Conflict::Predict { lhs, resolved_at: resolved_at @ None, .. } = conflict
+                {
+                    let sets = &select_sets[lhs];
+                    let disjoint = sets
+                        .iter()
+                        .enumerate()
+                        .all(|(index, set)| sets[index + 1..].iter().all(|other| set.is_disjoint(other)));
+                    if disjoint
+                    {
+                        *resolved_at = Some(larger);
+                    }
+                }
+            }
+        }
+        Err(Error::Conflicts(conflicts))
     }
 
-    pub fn parse(&self, program: String) -> Result<(), String>
+    // how many tokens of the lookahead some production of lhs predicts
+    fn matched_lookahead(&self, lhs: &Symbol, lookahead: &[Symbol]) -> usize
     {
+        self.parse_table
+            .keys()
+            .filter(|(symbol, _)| symbol == lhs)
+            .map(|(_, string)| string.iter().zip(lookahead).take_while(|(predicted, next)| predicted == next).count())
+            .max()
+            .unwrap_or(0)
+    }
 
-        let mut stack = Vec::<Symbol>::new();
-        let mut remaining_input = program
-            .split_whitespace()
-            .map(|x| Symbol::from(x.to_string()) )
-            .rev()
-            .collect::<Vec<Symbol>>();
-
-        stack.push(
-            Symbol{
-                label: String::from("Start"),
-                terminal: false
+    // hangs a finished value onto the innermost open node, reducing every node it completes
+    fn attach<T, A: Actions<T>>(&self, actions: &mut A, open_nodes: &mut Vec<PartialNode<T>>, mut value: Slot<T>, mut span: Span) -> Option<T>
+    {
+        while let Some(parent) = open_nodes.last_mut()
+        {
+            parent.children.push((value, span));
+            parent.span = Some(parent.span.map_or(span, |start| start.to(&span)));
+            if parent.children.len() < parent.arity
+            {
+                return None;
             }
-        );
+
+            let parent = open_nodes.pop().unwrap();
+            (value, span) = actions::reduce_slots(actions, &self.grammar, &parent.symbol, parent.rhs_id, parent.children, parent.span.unwrap());
+        }
+        Some(actions::finish(actions, &self.grammar.start, value, span))
+    }
+
+    // parses whitespace separated symbols
+    pub fn parse(&self, program: String) -> Result<ParseTree, Error>
+    {
+        self.parse_tokens(Token::split_whitespace(&program))
+    }
+
+    pub fn parse_tokens(&self, tokens: Vec<Token>) -> Result<ParseTree, Error>
+    {
+        self.parse_with(tokens, &mut TreeBuilder)
+    }
+
+    pub fn parse_with<T, A: Actions<T>>(&self, tokens: Vec<Token>, actions: &mut A) -> Result<T, Error>
+    {
+        let recovered = self.parse_recovering_with(tokens, actions, Recovery::Abort);
+        match (recovered.value, recovered.errors.into_iter().next())
+        {
+            (Some(value), None) => Ok(value),
+            (_, error) => Err(error.unwrap())
+        }
+    }
+
+    pub fn parse_recovering(&self, tokens: Vec<Token>, recovery: Recovery) -> Recovered<ParseTree>
+    {
+        self.parse_recovering_with(tokens, &mut TreeBuilder, recovery)
+    }
+
+    // parses on past errors as recovery says; each error message ends with the repair that was applied
+    pub fn parse_recovering_with<T, A: Actions<T>>(&self, tokens: Vec<Token>, actions: &mut A, recovery: Recovery) -> Recovered<T>
+    {
+        let end_span = tokens.last().map_or(Span::origin(), |token| token.end());
+        let mut errors = Vec::<Error>::new();
+
+        let mut stack = Vec::<Symbol>::new();
+        let mut open_nodes = Vec::<PartialNode<T>>::new();
+        let mut root: Option<T> = None;
+        let mut remaining_input = tokens;
+        remaining_input.reverse();
+
+        stack.push(self.grammar.start.clone());
 
         while !stack.is_empty()
         {
-            for symbol in stack.iter()
+            if self.trace
+            {
+                for symbol in stack.iter()
+                {
+                    print!("{} ", symbol);
+                }
+                print!("\t\t\t");
+                for token in remaining_input.iter().rev()
+                {
+                    print!(" {}", token.symbol);
+                }
+                println!();
+            }
+
+            let expected = stack.pop().unwrap();
+            let lookahead = remaining_input.iter().rev().take(self.k).map(|token| token.symbol.clone()).collect::<Vec<Symbol>>();
+            let lookahead_span = remaining_input.last().map_or(end_span, |token| token.span);
+
+            if expected.terminal
+            {
+                if lookahead.first() == Some(&expected)
+                {
+                    let incoming_token = remaining_input.pop().unwrap();
+                    let leaf = Slot::Value(actions.token(&incoming_token));
+                    root = self.attach(actions, &mut open_nodes, leaf, incoming_token.span);
+                    continue;
+                }
+            }
+            else if let Some(rhs_id) = self.parse_table.get(&(expected.clone(), lookahead))
             {
-                print!("{} ", symbol);
+                let rhs = &self.grammar.productions.get(&expected).unwrap()[*rhs_id as usize];
+                for symbol in rhs.iter().rev()
+                {
+                    stack.push(symbol.clone());
+                }
+
+                let node = PartialNode
+                {
+                    symbol: expected,
+                    rhs_id: *rhs_id,
+                    arity: rhs.len(),
+                    children: vec![],
+                    span: None
+                };
+                if node.arity == 0
+                {
+                    let (value, span) = actions::reduce_slots(actions, &self.grammar, &node.symbol, node.rhs_id, vec![], lookahead_span.collapsed());
+                    root = self.attach(actions, &mut open_nodes, value, span);
+                }
+                else
+                {
+                    open_nodes.push(node);
+                }
+                continue;
             }
-            print!("\t\t\t");
-            for symbol in remaining_input.iter().rev()
+
+            let mut error = self.unexpected(&expected, &remaining_input, end_span);
+            if recovery == Recovery::Abort
             {
-                print!(" {}", symbol);
+                errors.push(error);
+                return Recovered { value: None, errors };
             }
-            println!("");
 
-            let expected = stack.pop().unwrap();
+            stack.push(expected);
+            let repaired = match recovery
+            {
+                Recovery::PhraseLevel => self.repair(&stack, &mut remaining_input, end_span),
+                _ => None
+            };
+            let repair = match repaired
+            {
+                Some(repair) => repair,
+                None => {
+                    let (repair, value) = self.synchronize(actions, &mut stack, &mut open_nodes, &mut remaining_input, end_span);
+                    root = value;
+                    repair
+                }
+            };
+            if let Error::Parse { message, .. } = &mut error
+            {
+                message.push(' ');
+                message.push_str(&repair);
+            }
+            errors.push(error);
+        }
+
+        match remaining_input.last()
+        {
+            Some(token) => errors.push(Error::unexpected(&self.grammar, token.span, Some(token.symbol.clone()), vec![None])),
+            None if root.is_none() => errors.push(Error::unexpected(&self.grammar, end_span, None, vec![])),
+            None => ()
+        }
+        Recovered { value: root, errors }
+    }
+
+    // the error for expected not going on with the remaining input
+    fn unexpected(&self, expected: &Symbol, remaining_input: &[Token], end_span: Span) -> Error
+    {
+        let span_of = |index: usize| match remaining_input.len().checked_sub(index + 1)
+        {
+            Some(position) => remaining_input[position].span,
+            None => end_span
+        };
 
-            let lookahead = remaining_input
-                .last()
-                .ok_or(format!("Unexpected end of file; {} expected.", expected.label))?;
+        if expected.terminal
+        {
+            let found = remaining_input.last().map(|token| token.symbol.clone());
+            return Error::unexpected(&self.grammar, span_of(0), found, vec![Some(expected.clone())]);
+        }
+
+        // blame the first token no production can go on with, expecting what the predict row has after the tokens matched
+        let lookahead = remaining_input.iter().rev().take(self.k).map(|token| token.symbol.clone()).collect::<Vec<Symbol>>();
+        let index = self.matched_lookahead(expected, &lookahead);
+        let predicted = self.parse_table
+            .keys()
+            .filter(|(symbol, string)| symbol == expected && string.len() >= index && string[..index] == lookahead[..index])
+            .map(|(_, string)| string.get(index).cloned())
+            .collect::<Vec<Option<Symbol>>>();
+        Error::unexpected(&self.grammar, span_of(index), lookahead.get(index).cloned(), predicted)
+    }
+
+    // phrase-level recovery: deletes the next token, or failing that inserts a terminal before it,
+    // where the stack then parses the next REPAIR_TOKENS tokens
+    fn repair(&self, stack: &[Symbol], remaining_input: &mut Vec<Token>, end_span: Span) -> Option<String>
+    {
+        let input = remaining_input.iter().rev().map(|token| token.symbol.clone()).collect::<Vec<Symbol>>();
+        if !input.is_empty() && self.continues(stack, &input[1..], LLParser::REPAIR_TOKENS)
+        {
+            let token = remaining_input.pop().unwrap();
+            return Some(format!("Deleted {}.", token.symbol));
+        }
+
+        let mut terminals = self.grammar.terminals.iter().filter(|terminal| **terminal != Symbol::error()).collect::<Vec<&Symbol>>();
+        terminals.sort();
+        for terminal in terminals
+        {
+            let repaired = std::iter::once(terminal.clone()).chain(input.iter().cloned()).collect::<Vec<Symbol>>();
+            if self.continues(stack, &repaired, LLParser::REPAIR_TOKENS + 1)
+            {
+                let span = remaining_input.last().map_or(end_span, |token| token.span).collapsed();
+                remaining_input.push(Token
+                {
+                    symbol: terminal.clone(),
+                    lexeme: String::new(),
+                    span
+                });
+                return Some(format!("Inserted {}.", terminal));
+            }
+        }
+        None
+    }
+
+    // whether the stack, top last, parses the first count symbols of input, all of it where shorter
+    fn continues(&self, stack: &[Symbol], input: &[Symbol], count: usize) -> bool
+    {
+        let mut stack = stack.to_vec();
+        let mut consumed = 0;
+        while consumed < count
+        {
+            let expected = match stack.pop()
+            {
+                Some(expected) => expected,
+                None => return consumed == input.len()
+            };
 
             if expected.terminal
             {
-                let incoming_token = remaining_input.pop().unwrap();
-                if incoming_token != expected
+                if input.get(consumed) != Some(&expected)
                 {
-                    return Err(format!("Unexpected_token {}; {} expected", incoming_token, expected));
+                    return false;
                 }
+                consumed += 1;
             }
             else
             {
-                
-                let key = (expected, lookahead.clone());
-                let rhs_id = self.parse_table
-                    .get(&key)
-                    .ok_or(format!("Unexpected token {}; {} expected.", lookahead, key.0))?.clone();
-                let (expected, _) = key;
-
-                for symbol in self.grammar.productions.get(&expected).unwrap()[rhs_id as usize].iter().rev()
+                let lookahead = input[consumed..].iter().take(self.k).cloned().collect::<Vec<Symbol>>();
+                match self.parse_table.get(&(expected.clone(), lookahead))
                 {
-                    stack.push(symbol.clone());
+                    Some(rhs_id) => stack.extend(self.grammar.productions[&expected][*rhs_id as usize].iter().rev().cloned()),
+                    None => return false
                 }
+            }
+        }
+        true
+    }
+
+    // panic mode: a missing terminal is inserted, and a nonterminal skips tokens until it can go on, or one of
+    // its FOLLOW set or one starting a symbol below it comes, where it is given up on and an error leaf stands in for it.
+    // Returns the repair and the value of the parse if giving up finished it.
+    fn synchronize<T, A: Actions<T>>(&self, actions: &mut A, stack: &mut Vec<Symbol>, open_nodes: &mut Vec<PartialNode<T>>, remaining_input: &mut Vec<Token>, end_span: Span) -> (String, Option<T>)
+    {
+        let expected = stack.last().unwrap().clone();
+        if expected.terminal
+        {
+            let span = remaining_input.last().map_or(end_span, |token| token.span).collapsed();
+            remaining_input.push(Token
+            {
+                symbol: expected.clone(),
+                lexeme: String::new(),
+                span
+            });
+            return (format!("Inserted {}.", expected), None);
+        }
 
+        // tokens that may follow it, or start something further down the stack
+        let mut synchronizing = self.grammar.follow(&expected);
+        for symbol in stack[..stack.len() - 1].iter()
+        {
+            synchronizing.extend(self.grammar.first_of_symbol(symbol));
+        }
+        let mut skipped = 0;
+        loop
+        {
+            let lookahead = remaining_input.iter().rev().take(self.k).map(|token| token.symbol.clone()).collect::<Vec<Symbol>>();
+            if self.parse_table.contains_key(&(expected.clone(), lookahead))
+            {
+                return (format!("Skipped {}.", tokens(skipped)), None);
             }
+            match remaining_input.last()
+            {
+                Some(token) if !synchronizing.contains(&token.symbol) => {
+                    remaining_input.pop();
+                    skipped += 1;
+                },
+                _ => break
+            }
+        }
+
+        stack.pop();
+        let token = Token
+        {
+            symbol: Symbol::error(),
+            lexeme: String::new(),
+            span: remaining_input.last().map_or(end_span, |token| token.span).collapsed()
+        };
+        let leaf = Slot::Value(actions.token(&token));
+        let value = self.attach(actions, open_nodes, leaf, token.span);
+        match skipped
+        {
+            0 => (format!("Gave up on {}.", expected), value),
+            _ => (format!("Skipped {} and gave up on {}.", tokens(skipped), expected), value)
         }
+    }
+}
 
-        Ok(())
+fn tokens(count: usize) -> String
+{
+    match count
+    {
+        1 => String::from("1 token"),
+        count => format!("{} tokens", count)
     }
 }
 
 #[test]
 fn test_ll()
 {
-    let grammar = Grammar::from_file("data/bnf");
-    let parser = LLParser::new(grammar.clone()); 
+    let grammar = Grammar::from_file("data/bnf").unwrap();
+    let parser = LLParser::new(grammar.clone()).unwrap(); 
+
+    let tree = parser.parse(String::from("a b b d c $")).unwrap();
+
+    assert_eq!(tree.symbol().label, "Start");
+    assert_eq!(tree.lexemes(), vec!["a", "b", "b", "d", "c", "$"]);
+    // tracing only prints the steps
+    assert_eq!(LLParser::traced(grammar.clone(), 1).unwrap().parse(String::from("a b b d c $")).unwrap(), tree);
+
+    // S -> A C, A -> a B C d
+    let s = &tree.children()[0];
+    let a = &s.children()[0];
+    assert_eq!(a.symbol().label, "A");
+    assert!(matches!(a, ParseTree::Node { rhs_id: 0, .. }));
+    assert_eq!(a.children().len(), 4);
+
+    // the lambda production for the inner C has no children
+    assert!(a.children()[2].children().is_empty());
+}
+
+#[test]
+fn test_ll_actions()
+{
+    // records reductions in the order they happen
+    struct Trace(Vec<String>);
+    impl Actions<()> for Trace
+    {
+        fn token(&mut self, _token: &Token) { }
+
+        fn reduce(&mut self, lhs: &Symbol, rhs_id: u32, _children: Vec<()>, _span: Span)
+        {
+            self.0.push(format!("{} {}", lhs, rhs_id));
+        }
+    }
+
+    let grammar = Grammar::from_file("data/eeeee").unwrap();
+    let parser = LLParser::new(grammar).unwrap(); 
+    let mut trace = Trace(vec![]);
+
+    parser.parse_with(Token::split_whitespace("plus num num $"), &mut trace).unwrap();
+    assert_eq!(trace.0, vec!["E 1", "E 1", "E 0", "Start 0"]);
+}
+
+#[test]
+fn test_predict_conflict()
+{
+    let grammar = Grammar::from_file("data/self_referencing").unwrap();
+
+    match LLParser::new(grammar)
+    {
+        Err(Error::Conflicts(conflicts)) => {
+            assert_eq!(conflicts.len(), 1);
+            match &conflicts[0]
+            {
+                Conflict::Predict { lhs, lookahead, productions, resolved_at } => {
+                    assert_eq!(lhs.label, "E");
+                    assert_eq!(lookahead.len(), 1);
+                    assert_eq!(lookahead[0].label, "num");
+                    assert!(*productions == (0, 1) || *productions == (1, 0));
+                    // left recursion is beyond any lookahead
+                    assert_eq!(*resolved_at, None);
+                },
+                conflict => panic!("unexpected conflict {}", conflict)
+            }
+        },
+        _ => panic!("expected a predict conflict")
+    }
+}
+
+#[test]
+fn test_ll_spans()
+{
+    let grammar = Grammar::from_file("data/bnf").unwrap();
+    let parser = LLParser::new(grammar).unwrap(); 
+
+    let tree = parser.parse(String::from("a b\nd $")).unwrap();
+    let a = &tree.children()[0].children()[0];
+    assert_eq!(a.span(), Span { start: 0, end: 5, line: 1, column: 1 });
+
+    // the lambda production for C sits right before d
+    assert_eq!(a.children()[2].span(), Span { start: 4, end: 4, line: 2, column: 1 });
+
+    match parser.parse(String::from("a b\n  q $"))
+    {
+        Err(Error::Parse { span, .. }) => assert_eq!(span, Span { start: 6, end: 7, line: 2, column: 3 }),
+        result => panic!("unexpected {:?}", result)
+    }
+    match parser.parse(String::from("a b d"))
+    {
+        Err(Error::Parse { span, .. }) => assert_eq!(span, Span { start: 5, end: 5, line: 1, column: 6 }),
+        result => panic!("unexpected {:?}", result)
+    }
+}
+
+#[test]
+fn test_ll_k()
+{
+    use crate::lr_parser::{LRParser, Mode};
+
+    let grammar = Grammar::from_file("data/ll3").unwrap();
+    assert!(matches!(LLParser::with_lookahead(grammar.clone(), 0), Err(Error::Lookahead(0))));
+
+    for k in 1..3
+    {
+        match LLParser::with_lookahead(grammar.clone(), k)
+        {
+            Err(Error::Conflicts(conflicts)) => assert!(conflicts.iter().all(|conflict| matches!(conflict,
+                Conflict::Predict { resolved_at: Some(3), lookahead, .. } if lookahead.len() == k))),
+            _ => panic!("expected LL({}) conflicts", k)
+        }
+    }
+
+    let parser = LLParser::with_lookahead(grammar.clone(), 3).unwrap();
+    let lr_parser = LRParser::new(grammar, Mode::LALR).unwrap();
+    for program in &["id dot assign num $", "id dot lp rp $", "id lp rp $"]
+    {
+        assert_eq!(parser.parse(program.to_string()).unwrap(), lr_parser.parse(program.to_string()).unwrap());
+    }
+
+    // the lookahead runs out before k tokens near the end of the input
+    match parser.parse(String::from("id dot"))
+    {
+        Err(Error::Parse { message, .. }) => assert_eq!(message, "Expected one of `assign`, `lp`; found end of input."),
+        result => panic!("unexpected {:?}", result)
+    }
+    match parser.parse(String::from("id rp rp $"))
+    {
+        Err(Error::Parse { message, span, .. }) => {
+            assert_eq!(message, "Expected one of `dot`, `lp`; found `rp`.");
+            assert_eq!(span.start, 3);
+        },
+        result => panic!("unexpected {:?}", result)
+    }
+}
+
+#[test]
+fn test_ll_recovery()
+{
+    let parser = LLParser::new(Grammar::from_file("data/statements").unwrap()).unwrap();
+    let recover = |program: &str, recovery: Recovery| {
+        let recovered = parser.parse_recovering(Token::split_whitespace(program), recovery);
+        let messages = recovered.errors.iter().map(|error| error.to_string()).collect::<Vec<String>>();
+        (recovered.value.map(|tree| tree.lexemes().join(" ")), messages)
+    };
+
+    // a missing ; is inserted, a doubled num deleted
+    let (tree, messages) = recover("id = num id = num num ; $", Recovery::PhraseLevel);
+    assert_eq!(tree.unwrap(), "id = num  id = num ; $");
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].ends_with("Expected `;`; found `id`. Inserted ;."), "{}", messages[0]);
+    assert!(messages[1].ends_with("Expected `;`; found `num`. Deleted num."), "{}", messages[1]);
 
-    parser.parse(String::from("a b b d c $")).unwrap();
+    // no single token repair fits, so Expr skips to the ; that follows it
+    for recovery in [Recovery::PanicMode, Recovery::PhraseLevel].iter()
+    {
+        let (tree, messages) = recover("id = = = ; print num ; $", *recovery);
+        assert_eq!(tree.unwrap(), "id =  ; print num ; $");
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].ends_with("Expected one of `id`, `num`; found `=`. Skipped 2 tokens and gave up on Expr."), "{}", messages[0]);
+    }
 
-}
\ No newline at end of file
+    // panic mode inserts the missing terminal too, and aborting stops at the first error
+    let program = "print num print id ; id = $";
+    let (tree, messages) = recover(program, Recovery::PanicMode);
+    assert_eq!((tree.unwrap(), messages.len()), (String::from("print num  print id ; id =   $"), 3));
+    let (tree, messages) = recover(program, Recovery::Abort);
+    assert_eq!((tree, messages.len()), (None, 1));
+    assert_eq!(parser.parse(String::from(program)).err().map(|error| error.to_string()), Some(messages[0].clone()));
+}
@@ -1,21 +1,30 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use crate::symbol::Symbol;
-use crate::grammar::Grammar;
+use crate::grammar::{Associativity, Grammar};
+use crate::parse_tree::{ParseTree, TreeBuilder};
+use crate::actions::{self, Actions, Slot};
+use crate::error::{Conflict, Error, Recovered};
+use crate::token::{Span, Token};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode
 {
     LR0,
-    SLR
+    SLR,
+    LR1,
+    LALR
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
-struct StackSymbol
+struct StackSymbol<T>
 {
     symbol: Symbol,
-    state: u32
+    state: u32,
+    value: Slot<T>,
+    span: Span
 }
 
-impl std::fmt::Display for StackSymbol
+impl<T> std::fmt::Display for StackSymbol<T>
 {
     fn fmt(&self, f: &'_ mut std::fmt::Formatter) -> std::fmt::Result
     {
@@ -23,7 +32,7 @@ impl std::fmt::Display for StackSymbol
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialOrd, Ord)]
 struct BookmarkedRule
 {
     pub lhs: Symbol,
@@ -31,7 +40,10 @@ struct BookmarkedRule
 
     // the index of the next symbol in the rule to handle, or none if done
     pub bookmark: Option<u32>,
-    pub goto: Option<u32>
+    pub goto: Option<u32>,
+
+    // tokens that may follow the rule once reduced (LR(1) only, None is end of input)
+    pub lookahead: BTreeSet<Option<Symbol>>
 }
 
 impl Eq for BookmarkedRule { }
@@ -40,29 +52,53 @@ impl PartialEq for BookmarkedRule
 {
     fn eq(&self, other: &Self) -> bool
     {
-        return self.lhs == other.lhs && self.rhs_id == other.rhs_id && self.bookmark == other.bookmark;
+        self.same_core(other) && self.lookahead == other.lookahead
     }
 }
 
 impl BookmarkedRule
 {
-    #[allow(dead_code)]
-    fn print(&self, grammar: &Grammar)
+    fn same_core(&self, other: &Self) -> bool
+    {
+        self.lhs == other.lhs && self.rhs_id == other.rhs_id && self.bookmark == other.bookmark
+    }
+
+    // the dotted rule with its lookahead, without the goto
+    fn describe(&self, grammar: &Grammar) -> String
     {
-        print!("{} ->", self.lhs);
+        let mut out = format!("{} ->", self.lhs);
         for (index, s) in grammar.get_rhs(&self.lhs, self.rhs_id).unwrap().iter().enumerate()
         {
             if Some(index as u32) == self.bookmark
             {
-                print!(" ~");
+                out += " ~";
             }
 
-            print!(" {}", s);
+            out += &format!(" {}", s);
         }
-        if let None = self.bookmark
+        if self.bookmark.is_none()
         {
-            print!(" ~");
+            out += " ~";
+        }
+        if !self.lookahead.is_empty()
+        {
+            out += "    ,";
+            for symbol in &self.lookahead
+            {
+                match symbol
+                {
+                    Some(symbol) => out += &format!(" {}", symbol),
+                    None => out += " EOF"
+                }
+            }
         }
+        out
+    }
+
+    #[allow(dead_code)]
+    fn print(&self, grammar: &Grammar)
+    {
+        print!("{}", self.describe(grammar));
         if let Some(goto) = self.goto
         {
             print!("    goto {}", goto);
@@ -83,7 +119,7 @@ impl PartialEq for State
 {
     fn eq(&self, other: &Self) -> bool
     {
-        return self.kernel == other.kernel;
+        self.kernel == other.kernel
     }
 }
 
@@ -91,6 +127,22 @@ impl Eq for State {}
 
 impl State
 {
+    // the dotted rules of a conflict: the completed items of the reductions, and the items that shift `shifted`
+    fn conflicting_items(&self, grammar: &Grammar, reductions: &[&(Symbol, u32)], shifted: Option<&Symbol>) -> Vec<String>
+    {
+        let mut items = self.kernel.iter()
+            .chain(self.closure.iter())
+            .filter(|item| match item.bookmark
+            {
+                None => reductions.contains(&&(item.lhs.clone(), item.rhs_id)),
+                Some(bookmark) => shifted.is_some_and(|shifted| grammar.get_rhs(&item.lhs, item.rhs_id).unwrap()[bookmark as usize] == *shifted)
+            })
+            .map(|item| item.describe(grammar))
+            .collect::<Vec<String>>();
+        items.dedup();
+        items
+    }
+
     #[allow(dead_code)]
     fn print(&self, grammar: &Grammar)
     {
@@ -98,48 +150,78 @@ impl State
         for item in &self.kernel
         {
             print!(" ");
-            item.print(&grammar);
-            println!("");
+            item.print(grammar);
+            println!();
         }
         println!("------------------------------");
         for item in &self.closure
         {
             print!(" ");
-            item.print(&grammar);
-            println!("");
+            item.print(grammar);
+            println!();
         }
         println!("==============================");
     }
 }
 
 
-#[derive(Debug)]
-enum Action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Action
 {
     Shift(u32), // Shift (State)
     Reduce( (Symbol, u32) ), // Reduce (Rule)
     Accept
 }
 
+// how declared precedence settles a shift-reduce conflict
+#[derive(Debug)]
+enum Resolution
+{
+    Shift,
+    Reduce,
+    // %nonassoc: neither, the input is an error
+    Error
+}
+
 pub struct LRParser
 {
-    grammar: Grammar,
-    parse_table: HashMap<(u32, Option<Symbol>), Action >,
-    mode: Mode
+    pub(crate) grammar: Grammar,
+    pub(crate) parse_table: HashMap<(u32, Option<Symbol>), Action >,
+    pub(crate) mode: Mode,
+    // whether building the table and parsing print the states and stacks
+    pub(crate) trace: bool
 }
 
 impl LRParser
 {
-    pub fn new(grammar: Grammar, mode: Mode) -> LRParser
+    pub fn new(grammar: Grammar, mode: Mode) -> Result<LRParser, Error>
     {
-        let mut parser = LRParser{
-            grammar: grammar,
-            parse_table: HashMap::<(u32, Option<Symbol>), Action>::new(),
-            mode
-        };
+        LRParser::build(grammar, mode, false)
+    }
+
+    // for debugging: prints the states and precedence resolutions while building the table,
+    // and the stack at every step of a parse
+    pub fn traced(grammar: Grammar, mode: Mode) -> Result<LRParser, Error>
+    {
+        LRParser::build(grammar, mode, true)
+    }
+
+    fn build(grammar: Grammar, mode: Mode, trace: bool) -> Result<LRParser, Error>
+    {
+        let mut parser = LRParser::unbuilt(grammar, mode, trace);
+        parser.build_table()?;
+        Ok(parser)
+    }
 
-        parser.build_table();
-        parser
+    // a parser with an empty table, for fill_table
+    pub(crate) fn unbuilt(grammar: Grammar, mode: Mode, trace: bool) -> LRParser
+    {
+        LRParser{
+            grammar,
+            parse_table: HashMap::<(u32, Option<Symbol>), Action>::new(),
+            mode,
+            trace
+        }
     }
 
     fn get_rhs(&self, lhs: &Symbol, rhs_id: u32) -> Option<&Vec<Symbol>>
@@ -162,13 +244,14 @@ impl LRParser
             lhs,
             rhs_id,
             bookmark,
-            goto: None
+            goto: None,
+            lookahead: BTreeSet::new()
         }
     }
 
     fn build_state(&self, kernel: Vec<BookmarkedRule>, id: u32) -> State
     {
-        let closure = self.build_closure(&kernel);
+        let closure = self.build_closure(&kernel, matches!(self.mode, Mode::LR1));
         State
         {
             id,
@@ -178,152 +261,559 @@ impl LRParser
 
     }
 
-    fn build_closure(&self, kernel: &Vec<BookmarkedRule>) -> Vec<BookmarkedRule>
+    fn build_closure(&self, kernel: &[BookmarkedRule], with_lookahead: bool) -> Vec<BookmarkedRule>
     {
-
-        let mut consider_list = kernel
-            .clone()
-            .into_iter()
-            .collect::<HashSet<BookmarkedRule>>();
+        // items are merged by core, so with lookahead a single item carries every lookahead
+        let mut consider_list = kernel.to_vec();
 
         loop 
         {
-            let initial_length = consider_list.len().clone();
+            let mut changed = false;
+            let mut index = 0;
 
-            let mut new_items = Vec::<BookmarkedRule>::new();
-
-            for rule_to_consider in consider_list.iter()
+            while index < consider_list.len()
             {
+                let rule_to_consider = consider_list[index].clone();
+                index += 1;
+
                 if let Some(bookmark) = rule_to_consider.bookmark
                 {
-                    let next_symbol = &self.get_rhs(&rule_to_consider.lhs, rule_to_consider.rhs_id).unwrap()[bookmark as usize];
+                    let rhs = self.get_rhs(&rule_to_consider.lhs, rule_to_consider.rhs_id).unwrap();
+                    let next_symbol = &rhs[bookmark as usize];
                     if !next_symbol.terminal
                     {
                         if let Some(productions) = self.grammar.productions.get(next_symbol)
                         {
-                            new_items.append(
-                                &mut (0..productions.len()).map(
-                                    |index|
-                                    self.build_bookmarked_rule(next_symbol.clone(), index as u32)
-                                ).collect::<Vec<BookmarkedRule>>());
+                            let lookahead = if with_lookahead
+                            {
+                                self.closure_lookahead(&rhs[bookmark as usize + 1..], &rule_to_consider.lookahead)
+                            }
+                            else
+                            {
+                                BTreeSet::new()
+                            };
+
+                            for rhs_id in 0..productions.len()
+                            {
+                                let mut new_item = self.build_bookmarked_rule(next_symbol.clone(), rhs_id as u32);
+                                new_item.lookahead = lookahead.clone();
+
+                                if let Some(existing) = consider_list.iter_mut().find(|item| item.same_core(&new_item))
+                                {
+                                    if !new_item.lookahead.is_subset(&existing.lookahead)
+                                    {
+                                        existing.lookahead.extend(new_item.lookahead);
+                                        changed = true;
+                                    }
+                                }
+                                else
+                                {
+                                    consider_list.push(new_item);
+                                    changed = true;
+                                }
+                            }
                         }
                     }
                 }
             }
 
-            for item in new_items.into_iter()
+            if !changed
             {
-                consider_list.insert(item);
+                break;
             }
+        }
+
+        consider_list.split_off(kernel.len())
+    }
+
+    // lookahead of the items predicted for the symbol preceding `rest`
+    fn closure_lookahead(&self, rest: &[Symbol], parent_lookahead: &BTreeSet<Option<Symbol>>) -> BTreeSet<Option<Symbol>>
+    {
+        let rest = rest.to_vec();
+        let mut out = self.grammar.first_of_rhs(&rest)
+            .into_iter()
+            .map(Some)
+            .collect::<BTreeSet<Option<Symbol>>>();
+        if self.grammar.rhs_derives_lambda(&rest)
+        {
+            out.extend(parent_lookahead.iter().cloned());
+        }
+        out
+    }
+
+    // the bookmark of a rule once its next symbol has been shifted
+    fn advance_bookmark(&self, rule: &BookmarkedRule) -> Option<u32>
+    {
+        let length = self.get_rhs(&rule.lhs, rule.rhs_id).unwrap().len() as u32;
+        rule.bookmark.and_then(|index| if index + 1 == length { None } else { Some(index + 1) })
+    }
+
+    // LALR(1): spread lookaheads over the LR(0) states along the goto links of their items
+    fn propagate_lookaheads(&self, all_states: &mut [State])
+    {
+        // stands in for "whatever follows the kernel item"; no token read from input has an empty label
+        let marker = Some(Symbol
+        {
+            label: String::new(),
+            terminal: true
+        });
 
-            if consider_list.len() == initial_length
+        let mut lookaheads = all_states
+            .iter()
+            .map(|state| vec![BTreeSet::<Option<Symbol>>::new(); state.kernel.len()])
+            .collect::<Vec<Vec<BTreeSet<Option<Symbol>>>>>();
+        let mut links = Vec::<((usize, usize), (usize, usize))>::new();
+
+        for lookahead in lookaheads[0].iter_mut()
+        {
+            lookahead.insert(None);
+        }
+
+        // spontaneous lookaheads and propagation links
+        for (state_id, state) in all_states.iter().enumerate()
+        {
+            for (kernel_id, item) in state.kernel.iter().enumerate()
+            {
+                let mut probe = item.clone();
+                probe.lookahead = std::iter::once(marker.clone()).collect();
+                let probe = vec![probe];
+                let closure = self.build_closure(&probe, true);
+
+                for derived in probe.iter().chain(closure.iter())
+                {
+                    if derived.bookmark.is_none()
+                    {
+                        continue;
+                    }
+
+                    let goto = state.closure.iter()
+                        .chain(state.kernel.iter())
+                        .find(|rule| rule.same_core(derived))
+                        .and_then(|rule| rule.goto)
+                        .unwrap() as usize;
+                    let bookmark = self.advance_bookmark(derived);
+                    let target = all_states[goto].kernel
+                        .iter()
+                        .position(|rule| rule.lhs == derived.lhs && rule.rhs_id == derived.rhs_id && rule.bookmark == bookmark)
+                        .unwrap();
+
+                    for symbol in &derived.lookahead
+                    {
+                        if *symbol == marker
+                        {
+                            links.push( ((state_id, kernel_id), (goto, target)) );
+                        }
+                        else
+                        {
+                            lookaheads[goto][target].insert(symbol.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        loop
+        {
+            let mut changed = false;
+            for ((from_state, from_item), (to_state, to_item)) in links.iter()
+            {
+                let incoming = lookaheads[*from_state][*from_item].clone();
+                if !incoming.is_subset(&lookaheads[*to_state][*to_item])
+                {
+                    lookaheads[*to_state][*to_item].extend(incoming);
+                    changed = true;
+                }
+            }
+
+            if !changed
             {
                 break;
             }
         }
 
-        consider_list.retain(|x| !kernel.contains(x));
-        consider_list.into_iter().collect::<Vec<BookmarkedRule>>()
+        for (state, kernel_lookaheads) in all_states.iter_mut().zip(lookaheads)
+        {
+            for (item, lookahead) in state.kernel.iter_mut().zip(kernel_lookaheads)
+            {
+                item.lookahead = lookahead;
+            }
+
+            let closure = self.build_closure(&state.kernel, true);
+            for item in state.closure.iter_mut()
+            {
+                if let Some(with_lookahead) = closure.iter().find(|rule| rule.same_core(item))
+                {
+                    item.lookahead = with_lookahead.lookahead.clone();
+                }
+            }
+        }
+    }
+
+    // the terminals with an action in state, None for the end of input
+    fn expected(&self, state: u32) -> Vec<Option<Symbol>>
+    {
+        self.parse_table
+            .keys()
+            .filter(|(from, symbol)| *from == state && symbol.as_ref().is_none_or(|symbol| symbol.terminal && *symbol != Symbol::error()))
+            .map(|(_, symbol)| symbol.clone())
+            .collect()
+    }
+
+    // parses whitespace separated symbols
+    pub fn parse(&self, program: String) -> Result<ParseTree, Error>
+    {
+        self.parse_tokens(Token::split_whitespace(&program))
+    }
+
+    pub fn parse_tokens(&self, tokens: Vec<Token>) -> Result<ParseTree, Error>
+    {
+        self.parse_with(tokens, &mut TreeBuilder)
+    }
+
+    // stops at the first error
+    pub fn parse_with<T, A: Actions<T>>(&self, tokens: Vec<Token>, actions: &mut A) -> Result<T, Error>
+    {
+        self.drive(tokens, actions, None)
+    }
+
+    pub fn parse_recovering(&self, tokens: Vec<Token>) -> Recovered<ParseTree>
+    {
+        self.parse_recovering_with(tokens, &mut TreeBuilder)
+    }
+
+    // parses on past errors like yacc: the stack unwinds to a state that shifts the error token, which
+    // stands in for the input skipped, and tokens are discarded until one fits. Errors are reported
+    // again only once three tokens were shifted since the last one.
+    pub fn parse_recovering_with<T, A: Actions<T>>(&self, tokens: Vec<Token>, actions: &mut A) -> Recovered<T>
+    {
+        let mut errors = Vec::<Error>::new();
+        let value = self.drive(tokens, actions, Some(&mut errors)).ok();
+        Recovered { value, errors }
     }
 
-    pub fn parse(&self, program: String) -> Result<(), String>
+    // the parse loop: without a list to collect errors in, the first error ends the parse; with one, errors
+    // are recovered from and collected, and the result is an error only where recovery failed
+    fn drive<T, A: Actions<T>>(&self, tokens: Vec<Token>, actions: &mut A, mut errors: Option<&mut Vec<Error>>) -> Result<T, Error>
     {
+        let end_span = tokens.last().map_or(Span::origin(), |token| token.end());
+        // tokens still to shift before errors are reported again
+        let mut recovering = 0;
 
-        let mut handle = Vec::<StackSymbol>::new();
-        let mut remaining_input = program
-            .split_whitespace()
-            .map(|x| Symbol::from(x.to_string()) )
-            .rev()
-            .collect::<Vec<Symbol>>();
+        let mut handle = Vec::<StackSymbol<T>>::new();
+        let mut remaining_input = tokens;
+        remaining_input.reverse();
 
-        'parse: loop
+        // the nonterminal produced by the last reduction, consumed before any further input
+        let mut reduced: Option<(Symbol, Slot<T>, Span)> = None;
+
+        loop
         {
-            print!("handle:");
-            for stack_symbol in handle.iter()
-            {
-                print!(" {}", stack_symbol);
-            }
-            print!("\nremaining_input: ");
-            for symbol in remaining_input.iter().rev()
+            if self.trace
             {
-                print!(" {}", symbol);
+                print!("handle:");
+                for stack_symbol in handle.iter()
+                {
+                    print!(" {}", stack_symbol);
+                }
+                print!("\nremaining_input: ");
+                if let Some((symbol, _, _)) = &reduced
+                {
+                    print!(" {}", symbol);
+                }
+                for token in remaining_input.iter().rev()
+                {
+                    print!(" {}", token.symbol);
+                }
+                println!("\n");
             }
-            println!("\n");
 
             let current_state = handle.last().map(|s| s.state).unwrap_or(0);
-            let next_token = remaining_input.pop();
-            let temp = (current_state, next_token);
-            let action = &self.parse_table.get(&temp).ok_or( "parse error" )?;
-            let (_current_state, next_token) = temp;
+            let (next_symbol, next_span) = match &reduced
+            {
+                Some((symbol, _, span)) => (Some(symbol.clone()), *span),
+                None => match remaining_input.last()
+                {
+                    Some(token) => (Some(token.symbol.clone()), token.span),
+                    None => (None, end_span)
+                }
+            };
+            let temp = (current_state, next_symbol);
+            // Accept is the start symbol's entry, taken once the input was reduced to it, never for a
+            // token that happens to be labelled with the start symbol
+            let action = match self.parse_table.get(&temp).filter(|action| reduced.is_some() || !matches!(action, Action::Accept))
+            {
+                Some(action) => action,
+                None => {
+                    let error = Error::unexpected(&self.grammar, next_span, temp.1.clone(), self.expected(current_state));
+                    let errors = match errors.as_mut()
+                    {
+                        Some(errors) => errors,
+                        None => return Err(error)
+                    };
+                    if recovering == 3
+                    {
+                        // nothing was shifted since the error token: skip the token
+                        if remaining_input.pop().is_none()
+                        {
+                            return Err(error);
+                        }
+                        continue;
+                    }
+                    if recovering == 0
+                    {
+                        errors.push(error.clone());
+                    }
+                    recovering = 3;
+                    reduced = None;
+
+                    let error_symbol = Symbol::error();
+                    loop
+                    {
+                        let state = handle.last().map(|s| s.state).unwrap_or(0);
+                        if let Some(Action::Shift(next_state)) = self.parse_table.get(&(state, Some(error_symbol.clone())))
+                        {
+                            let token = Token
+                            {
+                                symbol: error_symbol.clone(),
+                                lexeme: String::new(),
+                                span: next_span.collapsed()
+                            };
+                            handle.push(StackSymbol
+                            {
+                                value: Slot::Value(actions.token(&token)),
+                                symbol: error_symbol,
+                                state: *next_state,
+                                span: token.span
+                            });
+                            break;
+                        }
+                        if handle.pop().is_none()
+                        {
+                            return Err(error);
+                        }
+                    }
+                    continue;
+                }
+            };
             match action
             {
                 Action::Shift(state) => {
+                    let (symbol, value, span) = match reduced.take()
+                    {
+                        Some(reduced) => reduced,
+                        None => {
+                            let token = remaining_input.pop().unwrap();
+                            let value = Slot::Value(actions.token(&token));
+                            recovering = recovering.max(1) - 1;
+                            (token.symbol, value, token.span)
+                        }
+                    };
                     handle.push(
                         StackSymbol
                         {
-                            symbol: next_token.unwrap(),
-                            state: *state
+                            symbol,
+                            state: *state,
+                            value,
+                            span
                         }
                     );
                 },
                 Action::Reduce( (lhs, rhs_id) ) => {
-                    for item in self.get_rhs(lhs, *rhs_id).unwrap().iter().rev()
+                    let mut children = Vec::<(Slot<T>, Span)>::new();
+                    let mut span = next_span.collapsed();
+                    for (index, item) in self.get_rhs(lhs, *rhs_id).unwrap().iter().rev().enumerate()
                     {
-                        assert_eq!(handle.pop().unwrap().symbol, *item);
+                        let stack_symbol = handle.pop().unwrap();
+                        assert_eq!(stack_symbol.symbol, *item);
+                        span = if index == 0 { stack_symbol.span } else { stack_symbol.span.to(&span) };
+                        children.push((stack_symbol.value, stack_symbol.span));
                     }
-                    
-                    if let Some(next_token) = next_token
-                    {
-                        remaining_input.push(next_token);
-                    }
-                    remaining_input.push(lhs.clone());
+                    children.reverse();
+
+                    let (value, span) = actions::reduce_slots(actions, &self.grammar, lhs, *rhs_id, children, span);
+                    reduced = Some((lhs.clone(), value, span));
                 },
                 Action::Accept => {
-                    break 'parse
+                    // the start symbol is complete, so whatever follows is an error that cannot be recovered from
+                    if let Some(token) = remaining_input.last()
+                    {
+                        let error = Error::unexpected(&self.grammar, token.span, Some(token.symbol.clone()), vec![None]);
+                        match errors.as_mut()
+                        {
+                            Some(errors) => errors.push(error),
+                            None => return Err(error)
+                        }
+                    }
+                    let (symbol, value, span) = reduced.unwrap();
+                    return Ok(actions::finish(actions, &symbol, value, span));
                 }
             }
         }
-
-        Ok(())
     }
 
-    fn add_state<'b>(&self, all_states:&mut Vec<State>, work_list: &mut Vec<u32>, kernel: Vec<BookmarkedRule>) -> u32 
+    fn add_state(&self, all_states:&mut Vec<State>, work_list: &mut Vec<u32>, kernel: Vec<BookmarkedRule>) -> u32 
     {
 
         let potential_new_state = self.build_state(kernel, all_states.len() as u32);
         if let Some(position) = all_states.iter().position( |state| *state == potential_new_state )
         {
-            return position as u32;
+            position as u32
         }
         else
         {
             let result = potential_new_state.id;
             all_states.push(potential_new_state);
             work_list.push(result);
-            return result;
+            result
+        }
+
+    }
+
+    // the shortest symbols that lead from the start state to each state, along the gotos of the items
+    fn shortest_prefixes(all_states: &[State], grammar: &Grammar) -> HashMap<u32, Vec<Symbol>>
+    {
+        let mut prefixes = HashMap::<u32, Vec<Symbol>>::new();
+        prefixes.insert(0, vec![]);
+        let mut queue = std::collections::VecDeque::<u32>::new();
+        queue.push_back(0);
+
+        while let Some(state_id) = queue.pop_front()
+        {
+            let state = &all_states[state_id as usize];
+            for item in state.kernel.iter().chain(state.closure.iter())
+            {
+                if let (Some(bookmark), Some(goto)) = (item.bookmark, item.goto)
+                {
+                    if !prefixes.contains_key(&goto)
+                    {
+                        let mut prefix = prefixes[&state_id].clone();
+                        prefix.push(grammar.get_rhs(&item.lhs, item.rhs_id).unwrap()[bookmark as usize].clone());
+                        prefixes.insert(goto, prefix);
+                        queue.push_back(goto);
+                    }
+                }
+            }
         }
+        prefixes
+    }
 
+    // a shortest terminal string each nonterminal derives, preferring earlier productions on ties
+    fn shortest_yields(grammar: &Grammar) -> HashMap<Symbol, Vec<Symbol>>
+    {
+        let mut yields = HashMap::<Symbol, Vec<Symbol>>::new();
+        let mut nonterminals = grammar.productions.keys().collect::<Vec<&Symbol>>();
+        nonterminals.sort();
+
+        let mut changed = true;
+        while changed
+        {
+            changed = false;
+            for lhs in nonterminals.iter()
+            {
+                for rhs in grammar.productions[*lhs].iter()
+                {
+                    let mut derived = Vec::<Symbol>::new();
+                    for symbol in rhs
+                    {
+                        match (symbol.terminal, yields.get(symbol))
+                        {
+                            (true, _) => derived.push(symbol.clone()),
+                            (false, Some(string)) => derived.extend(string.iter().cloned()),
+                            (false, None) => break
+                        }
+                    }
+                    let complete = rhs.iter().all(|symbol| symbol.terminal || yields.contains_key(symbol));
+                    if complete && yields.get(*lhs).is_none_or(|known| derived.len() < known.len())
+                    {
+                        yields.insert((*lhs).clone(), derived);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        yields
     }
 
-    fn build_table(&mut self)
+    fn build_table(&mut self) -> Result<(), Error>
+    {
+        let mut conflicts = self.fill_table();
+
+        // merging LR(1) states can only introduce reduce-reduce conflicts
+        if let Mode::LALR = self.mode
+        {
+            if conflicts.iter().any(|conflict| matches!(conflict, Conflict::ReduceReduce { .. }))
+            {
+                let mut canonical = LRParser::unbuilt(self.grammar.clone(), Mode::LR1, false);
+                let canonical_conflicts = canonical.fill_table();
+
+                for conflict in conflicts.iter_mut()
+                {
+                    let inherent = canonical_conflicts.iter().any(|canonical| canonical.same_reductions(conflict));
+                    if let Conflict::ReduceReduce { lalr_specific, .. } = conflict
+                    {
+                        *lalr_specific = Some(!inherent);
+                    }
+                }
+            }
+        }
+
+        if conflicts.is_empty()
+        {
+            Ok(())
+        }
+        else
+        {
+            Err(Error::Conflicts(conflicts))
+        }
+    }
+
+    // compares the rule's precedence with the lookahead terminal's, like yacc
+    fn resolve_shift_reduce(&self, symbol: &Option<Symbol>, lhs: &Symbol, rhs_id: u32) -> Option<Resolution>
+    {
+        let (symbol_level, associativity) = self.grammar.precedence.get(symbol.as_ref()?)?;
+        let (rule_level, _) = self.grammar.rule_precedence(lhs, rhs_id)?;
+
+        Some(if rule_level > *symbol_level
+        {
+            Resolution::Reduce
+        }
+        else if rule_level < *symbol_level
+        {
+            Resolution::Shift
+        }
+        else
+        {
+            match associativity
+            {
+                Associativity::Left => Resolution::Reduce,
+                Associativity::Right => Resolution::Shift,
+                Associativity::NonAssoc => Resolution::Error
+            }
+        })
+    }
+
+    pub(crate) fn fill_table(&mut self) -> Vec<Conflict>
     {
         let mut all_states = Vec::<State>::new();
         let mut work_list = Vec::<u32>::new();
-        let mut error_messages = Vec::<String>::new();
+        let mut conflicts = Vec::<Conflict>::new();
 
-        // Push Start into known states. 
-        let start_symbol = Symbol
+        // Push the start symbol's productions into known states. 
+        let start_symbol = self.grammar.start.clone();
+        // LALR lookaheads are only added once the LR(0) states are known
+        let start_lookahead = match self.mode
         {
-            label: String::from("Start"),
-            terminal: false
+            Mode::LR1 => vec![None].into_iter().collect::<BTreeSet<Option<Symbol>>>(),
+            _ => BTreeSet::new()
         };
-        let kernel = vec![BookmarkedRule
-        {
-            lhs: start_symbol.clone(),
-            rhs_id: 0,
-            bookmark: Some(0),
-            goto: None
-        }];
+        let mut kernel = (0..self.grammar.productions[&start_symbol].len())
+            .map(|rhs_id| BookmarkedRule
+            {
+                lookahead: start_lookahead.clone(),
+                ..self.build_bookmarked_rule(start_symbol.clone(), rhs_id as u32)
+            })
+            .collect::<Vec<BookmarkedRule>>();
+        kernel.sort();
         all_states.push(self.build_state(kernel, 0));
         work_list.push(0);
 
@@ -366,7 +856,7 @@ impl LRParser
                             }
                             else
                             {
-                                Some(index + 1 as u32)
+                                Some(index + 1)
                             }
 
                         }
@@ -374,14 +864,15 @@ impl LRParser
                         {
                             None
                         };
-                        new_kernels[index as usize].0.push(
+                        new_kernels[index].0.push(
                             rule_index as u32
                         );
-                        new_kernels[index as usize].1.push(BookmarkedRule{
+                        new_kernels[index].1.push(BookmarkedRule{
                             lhs: rule.lhs.clone(),
                             rhs_id: rule.rhs_id,
                             bookmark: new_bookmark,
-                            goto: None
+                            goto: None,
+                            lookahead: rule.lookahead.clone()
                         });
                     }
                 }
@@ -403,21 +894,43 @@ impl LRParser
             }
         }
 
-        for (index, state) in all_states.iter().enumerate()
+        if let Mode::LALR = self.mode
+        {
+            self.propagate_lookaheads(&mut all_states);
+        }
+
+        if self.trace
         {
-            println!("\nState: {}", index);
-            state.print(&self.grammar);
+            for (index, state) in all_states.iter().enumerate()
+            {
+                println!("\nState: {}", index);
+                state.print(&self.grammar);
+            }
         }
 
+        // counterexamples for conflicts: the shortest way into each state, as symbols and as input
+        let prefixes = LRParser::shortest_prefixes(&all_states, &self.grammar);
+        let yields = LRParser::shortest_yields(&self.grammar);
+        let example = |state: u32| {
+            let prefix = prefixes.get(&state).cloned().unwrap_or_default();
+            let input = prefix
+                .iter()
+                .flat_map(|symbol| yields.get(symbol).cloned().unwrap_or_else(|| vec![symbol.clone()]))
+                .collect::<Vec<Symbol>>();
+            (prefix, input)
+        };
+
         // REDUCES
         self.parse_table.insert( (0, Some(start_symbol)), Action::Accept);
+        // entries a %nonassoc resolution left empty, so no later reduce fills them
+        let mut nonassoc_errors = HashSet::<(u32, Option<Symbol>)>::new();
 
         for state in all_states.iter()
         {
             let rules_to_check = state.closure.iter().chain(state.kernel.iter());
 
             for rule in rules_to_check{
-                if let None = rule.bookmark{
+                if rule.bookmark.is_none(){
 
                     let reduce_set = match &self.mode
                     {
@@ -425,16 +938,21 @@ impl LRParser
                             self.grammar.terminals.iter()
                                 .chain(
                                     self.grammar.nonterminals.iter()
-                                    .filter(|x| x.label != "Start")
+                                    .filter(|x| **x != self.grammar.start)
                                 )
                                 .map(|symbol| Some(symbol.clone()))
-                                .chain(vec![None].into_iter())
+                                .chain(vec![None])
                                 .collect::<HashSet<Option<Symbol>>>()
                         },
                         Mode::SLR => {
                             self.grammar.follow(&rule.lhs).into_iter()
-                                .map(|symbol| Some(symbol))
-                                .chain(vec![None].into_iter())
+                                .map(Some)
+                                .chain(vec![None])
+                                .collect::<HashSet<Option<Symbol>>>()
+                        },
+                        Mode::LR1 | Mode::LALR => {
+                            rule.lookahead.iter()
+                                .cloned()
                                 .collect::<HashSet<Option<Symbol>>>()
                         }
                     };
@@ -443,19 +961,61 @@ impl LRParser
                     for symbol in reduce_set
                     {
                         let table_tuple = (state.id, symbol);
-                        if let Some(action) = self.parse_table.get( &table_tuple )
+                        if nonassoc_errors.contains(&table_tuple)
+                        {
+                            continue;
+                        }
+                        if let Some(action) = self.parse_table.get( &table_tuple ).cloned()
                         {
-                            let (_, symbol) = table_tuple;
                             match action
                             {
                                 Action::Shift(_next_state) => {
-                                    error_messages.push(format!("Shift-reduce conflict at state {} with symbol {:?}.", state.id, symbol));
+                                    let resolution = self.resolve_shift_reduce(&table_tuple.1, &rule.lhs, rule.rhs_id);
+                                    if let Some(resolution) = resolution.as_ref().filter(|_| self.trace)
+                                    {
+                                        println!("Resolved shift-reduce conflict at state {} with symbol {} (reduce {} {}) as {:?}.",
+                                            state.id, table_tuple.1.as_ref().unwrap(), rule.lhs, rule.rhs_id, resolution);
+                                    }
+
+                                    match resolution
+                                    {
+                                        Some(Resolution::Shift) => {},
+                                        Some(Resolution::Reduce) => {
+                                            self.parse_table.insert( table_tuple, Action::Reduce( (rule.lhs.clone(), rule.rhs_id) ));
+                                        },
+                                        Some(Resolution::Error) => {
+                                            self.parse_table.remove(&table_tuple);
+                                            nonassoc_errors.insert(table_tuple);
+                                        },
+                                        None => {
+                                            let rule = (rule.lhs.clone(), rule.rhs_id);
+                                            let (prefix, input) = example(state.id);
+                                            conflicts.push(Conflict::ShiftReduce {
+                                                state: state.id,
+                                                items: state.conflicting_items(&self.grammar, &[&rule], table_tuple.1.as_ref()),
+                                                symbol: table_tuple.1,
+                                                rule,
+                                                prefix,
+                                                input
+                                            });
+                                        }
+                                    }
                                 },
-                                Action::Reduce(_rule_id) => {
-                                    error_messages.push(format!("Reduce-reduce conflict at state {} with symbol {:?}.", state.id, symbol));
+                                Action::Reduce(rule_id) => {
+                                    let rules = (rule_id, (rule.lhs.clone(), rule.rhs_id));
+                                    let (prefix, input) = example(state.id);
+                                    conflicts.push(Conflict::ReduceReduce {
+                                        state: state.id,
+                                        symbol: table_tuple.1,
+                                        items: state.conflicting_items(&self.grammar, &[&rules.0, &rules.1], None),
+                                        rules,
+                                        prefix,
+                                        input,
+                                        lalr_specific: None
+                                    });
                                 },
                                 Action::Accept => {
-                                    error_messages.push(format!("What in the world!? State {}, symbol {:?}", state.id, symbol));
+                                    conflicts.push(Conflict::Accept { state: state.id, symbol: table_tuple.1 });
                                 }
                             }
                         }
@@ -468,19 +1028,7 @@ impl LRParser
            }
         }
 
-        if !error_messages.is_empty()
-        {
-            let mut error_string = String::from("\n");
-            for message in error_messages
-            {
-                error_string += &message[..];
-                error_string += "\n";
-            }
-
-            panic!(error_string);
-        }
-
-
+        conflicts
     }
 
 
@@ -490,8 +1038,8 @@ impl LRParser
 #[test]
 fn test_lr0_failure()
 {
-        let grammar = Grammar::from_file("data/bnf");
-        let parser = LRParser::new(grammar.clone(), Mode::LR0); 
+        let grammar = Grammar::from_file("data/bnf").unwrap();
+        let parser = LRParser::new(grammar.clone(), Mode::LR0).unwrap(); 
 
 
         let lhs = Symbol
@@ -505,7 +1053,8 @@ fn test_lr0_failure()
             lhs: lhs.clone(),
             rhs_id: 0,
             bookmark: Some(1),
-            goto: None
+            goto: None,
+            lookahead: BTreeSet::new()
         }];
 
 
@@ -515,8 +1064,8 @@ fn test_lr0_failure()
 #[test]
 fn test_slr_success()
 {
-    let grammar = Grammar::from_file("data/bnf");
-    let parser = LRParser::new(grammar.clone(), Mode::SLR); 
+    let grammar = Grammar::from_file("data/bnf").unwrap();
+    let parser = LRParser::new(grammar.clone(), Mode::SLR).unwrap(); 
 
 
     for (key, value) in parser.parse_table.iter()
@@ -536,19 +1085,22 @@ fn test_slr_success()
 
 }
 
-#[should_panic]
 #[test]
 fn multiple_conflicts_reported()
 {
-    let grammar = Grammar::from_file("data/10a");
-    let _parser = LRParser::new(grammar, Mode::LR0);
+    let grammar = Grammar::from_file("data/10a").unwrap();
+    match LRParser::new(grammar, Mode::LR0)
+    {
+        Err(Error::Conflicts(conflicts)) => assert!(conflicts.len() > 1),
+        _ => panic!("expected conflicts")
+    }
 }
 
 #[test]
 fn test_state_building()
 {
-    let grammar = Grammar::from_file("data/eeeee");
-    let parser = LRParser::new(grammar.clone(), Mode::LR0); 
+    let grammar = Grammar::from_file("data/eeeee").unwrap();
+    let parser = LRParser::traced(grammar.clone(), Mode::LR0).unwrap(); 
 
     for (key, value) in parser.parse_table.iter()
     {
@@ -563,15 +1115,25 @@ fn test_state_building()
         }
     }
 
-    parser.parse(String::from("plus plus num num num $")).unwrap();
+    let tree = parser.parse(String::from("plus plus num num num $")).unwrap();
+
+    assert_eq!(tree.symbol().label, "Start");
+    assert_eq!(tree.lexemes(), vec!["plus", "plus", "num", "num", "num", "$"]);
+
+    // E -> plus E E, where the first operand is itself a sum
+    let e = &tree.children()[0];
+    assert!(matches!(e, ParseTree::Node { rhs_id: 0, .. }));
+    assert!(matches!(&e.children()[1], ParseTree::Node { rhs_id: 0, .. }));
+    assert!(matches!(&e.children()[2], ParseTree::Node { rhs_id: 1, .. }));
 
 
 }
+
 #[test]
 fn test_neverending()
 {
-        let grammar = Grammar::from_file("data/self_referencing");
-        let parser = LRParser::new(grammar.clone(), Mode::LR0); 
+        let grammar = Grammar::from_file("data/self_referencing").unwrap();
+        let parser = LRParser::new(grammar.clone(), Mode::LR0).unwrap(); 
 
 
         let lhs = Symbol
@@ -585,9 +1147,309 @@ fn test_neverending()
             lhs: lhs.clone(),
             rhs_id: 0,
             bookmark: Some(0),
-            goto: None
+            goto: None,
+            lookahead: BTreeSet::new()
         }];
 
         parser.build_state(kernel, 0).print(&grammar);
 
 }
+
+#[test]
+fn test_slr_failure_on_lr1_grammar()
+{
+    let grammar = Grammar::from_file("data/lr1").unwrap();
+    assert!(LRParser::new(grammar, Mode::SLR).is_err());
+}
+
+#[test]
+fn test_lr1_success()
+{
+    let grammar = Grammar::from_file("data/lr1").unwrap();
+    let parser = LRParser::new(grammar.clone(), Mode::LR1).unwrap(); 
+
+    parser.parse(String::from("a c d $")).unwrap();
+    parser.parse(String::from("a c e $")).unwrap();
+    parser.parse(String::from("b c d $")).unwrap();
+    parser.parse(String::from("b c e $")).unwrap();
+    assert!(parser.parse(String::from("a c $")).is_err());
+}
+
+#[test]
+fn test_lr1_matches_slr()
+{
+    let grammar = Grammar::from_file("data/bnf").unwrap();
+    let parser = LRParser::new(grammar.clone(), Mode::LR1).unwrap(); 
+
+    parser.parse(String::from("a b b d c $")).unwrap();
+    parser.parse(String::from("b b q $")).unwrap();
+}
+
+#[test]
+fn test_slr_failure_on_lalr_grammar()
+{
+    let grammar = Grammar::from_file("data/lalr").unwrap();
+    assert!(LRParser::new(grammar, Mode::SLR).is_err());
+}
+
+#[test]
+fn test_lalr_success()
+{
+    let grammar = Grammar::from_file("data/lalr").unwrap();
+    let parser = LRParser::new(grammar.clone(), Mode::LALR).unwrap(); 
+
+    parser.parse(String::from("star id eq id $")).unwrap();
+    parser.parse(String::from("star star id $")).unwrap();
+    assert!(parser.parse(String::from("id eq eq id $")).is_err());
+
+    let parser = LRParser::new(Grammar::from_file("data/bnf").unwrap(), Mode::LALR).unwrap(); 
+    parser.parse(String::from("a b b d c $")).unwrap();
+}
+
+#[test]
+fn test_lalr_specific_conflict()
+{
+    let grammar = Grammar::from_file("data/lr1").unwrap();
+    let error = LRParser::new(grammar, Mode::LALR).err().unwrap();
+
+    match &error
+    {
+        Error::Conflicts(conflicts) => {
+            assert!(!conflicts.is_empty());
+            for conflict in conflicts
+            {
+                assert!(matches!(conflict, Conflict::ReduceReduce { lalr_specific: Some(true), .. }));
+            }
+        },
+        _ => panic!("expected conflicts")
+    }
+    assert!(error.to_string().contains("LALR-specific"));
+}
+
+#[test]
+fn test_lalr_inherent_conflict()
+{
+    let grammar = Grammar::from_file("data/10a").unwrap();
+
+    match LRParser::new(grammar, Mode::LALR)
+    {
+        Err(Error::Conflicts(conflicts)) => {
+            assert!(conflicts.iter().any(|conflict| matches!(conflict, Conflict::ShiftReduce { .. })));
+        },
+        _ => panic!("expected conflicts")
+    }
+}
+
+#[test]
+fn test_actions()
+{
+    // counts the operands of a prefix sum
+    struct Count;
+    impl Actions<u32> for Count
+    {
+        fn token(&mut self, token: &Token) -> u32
+        {
+            if token.symbol.label == "num" { 1 } else { 0 }
+        }
+
+        fn reduce(&mut self, _lhs: &Symbol, _rhs_id: u32, children: Vec<u32>, _span: Span) -> u32
+        {
+            children.iter().sum()
+        }
+    }
+
+    let grammar = Grammar::from_file("data/eeeee").unwrap();
+    let parser = LRParser::new(grammar, Mode::LALR).unwrap(); 
+
+    assert_eq!(parser.parse_with(Token::split_whitespace("plus plus num num num $"), &mut Count).unwrap(), 3);
+}
+
+#[test]
+fn test_spans()
+{
+    let grammar = Grammar::from_file("data/eeeee").unwrap();
+    let parser = LRParser::new(grammar, Mode::LALR).unwrap(); 
+
+    let tree = parser.parse(String::from("plus num\n  num $")).unwrap();
+    let e = &tree.children()[0];
+    assert_eq!(e.span(), Span { start: 0, end: 14, line: 1, column: 1 });
+    assert_eq!(e.children()[2].span(), Span { start: 11, end: 14, line: 2, column: 3 });
+
+    match parser.parse(String::from("plus num\n num num $"))
+    {
+        Err(Error::Parse { span, .. }) => assert_eq!(span, Span { start: 14, end: 17, line: 2, column: 6 }),
+        result => panic!("unexpected {:?}", result)
+    }
+}
+
+#[test]
+fn test_declared_start_symbol()
+{
+    use crate::lexer::{Lexer, TokenRule};
+
+    let grammar = Grammar::from_file("data/calc").unwrap();
+    let parser = LRParser::new(grammar, Mode::LALR).unwrap(); 
+    let lexer = Lexer::new(vec![
+        TokenRule::token("NUM", "[0-9]+"),
+        TokenRule::token("+", "\\+"),
+        TokenRule::token("*", "\\*"),
+        TokenRule::token("(", "\\("),
+        TokenRule::token(")", "\\)"),
+        TokenRule::skip("space", " +")
+    ]).unwrap();
+
+    let tree = parser.parse_tokens(lexer.tokenize("1 + 2*(3+4)").unwrap()).unwrap();
+    assert_eq!(tree.symbol().label, "Program");
+    assert_eq!(tree.lexemes(), vec!["1", "+", "2", "*", "(", "3", "+", "4", ")", ""]);
+}
+
+#[test]
+fn test_ebnf_lists()
+{
+    use crate::ll_parser::LLParser;
+
+    let grammar = Grammar::from_file("data/ebnf").unwrap();
+    let lr_parser = LRParser::new(grammar.clone(), Mode::LALR).unwrap();
+    let ll_parser = LLParser::new(grammar).unwrap();
+
+    let program = String::from("[ num , < num num num > , [ ] , num ] $");
+    let tree = lr_parser.parse(program.clone()).unwrap();
+    assert_eq!(tree, ll_parser.parse(program).unwrap());
+
+    // List -> [ (Item (, Item)*)? ]
+    let list = &tree.children()[0];
+    let optional = &list.children()[1];
+    assert_eq!(optional.symbol().label, "(Item (, Item)*)?");
+    assert_eq!(optional.children().len(), 1);
+
+    // every repetition is one node with a child per element
+    let rest = &optional.children()[0].children()[1];
+    assert_eq!(rest.symbol().label, "(, Item)*");
+    assert_eq!(rest.children().len(), 3);
+    assert!(rest.children().iter().all(|element| element.symbol().label == "(, Item)"));
+    assert_eq!(rest.span(), Span { start: 6, end: 35, line: 1, column: 7 });
+
+    let numbers = &rest.children()[0].children()[1].children()[1];
+    assert_eq!(numbers.symbol().label, "num+");
+    assert_eq!(numbers.lexemes(), vec!["num", "num", "num"]);
+    assert_eq!(numbers.children().len(), 3);
+
+    // the empty list has no elements
+    let empty = &rest.children()[1].children()[1].children()[0].children()[1];
+    assert!(empty.children().is_empty());
+}
+
+#[test]
+fn test_precedence()
+{
+    let grammar = Grammar::from_file("data/precedence").unwrap();
+    assert!(LRParser::new(grammar.clone(), Mode::SLR).is_ok());
+    let parser = LRParser::new(grammar, Mode::LALR).unwrap();
+
+    // the production each E node was built by, in prefix order
+    fn shape(tree: &ParseTree) -> String
+    {
+        match tree
+        {
+            ParseTree::Node { symbol, rhs_id, children, .. } if symbol.label == "E" => {
+                let operands = children.iter().filter(|child| child.symbol().label == "E").map(shape).collect::<Vec<String>>();
+                if operands.is_empty() { String::from("num") } else { format!("{}({})", rhs_id, operands.join(" ")) }
+            },
+            tree => shape(&tree.children()[0])
+        }
+    }
+    let parse = |program: &str| shape(&parser.parse(String::from(program)).unwrap());
+
+    // left associative, then tighter binding, then right associative
+    assert_eq!(parse("num - num - num $"), "1(1(num num) num)");
+    assert_eq!(parse("num + num * num $"), "0(num 2(num num))");
+    assert_eq!(parse("num ^ num ^ num $"), "4(num 4(num num))");
+    // %prec makes unary minus bind tighter than *
+    assert_eq!(parse("- num * num $"), "2(6(num) num)");
+
+    // non-associative operators cannot be chained
+    assert!(parser.parse(String::from("num < num $")).is_ok());
+    assert!(matches!(parser.parse(String::from("num < num < num $")), Err(Error::Parse { .. })));
+}
+
+#[test]
+fn test_error_recovery()
+{
+    let grammar = Grammar::from_file("data/recovery").unwrap();
+    let parser = LRParser::new(grammar, Mode::LALR).unwrap();
+
+    let program = "id = num ; id = = ; id = num ; id num ; $";
+    let recovered = parser.parse_recovering(Token::split_whitespace(program));
+    let messages = recovered.errors.iter().map(|error| error.to_string()).collect::<Vec<String>>();
+    assert_eq!(recovered.errors.len(), 2, "{:?}", messages);
+    assert!(messages[0].ends_with("Expected a number; found `=`."), "{}", messages[0]);
+    match &recovered.errors[1]
+    {
+        Error::Parse { span, found, expected, message } => {
+            assert_eq!(message, "Expected `=`; found a number.");
+            assert_eq!((found.clone(), expected.clone()), (Some(Symbol::from(String::from("num"))), vec![Some(Symbol::from(String::from("=")))]));
+            assert_eq!(span.start, 34);
+        },
+        error => panic!("unexpected error {}", error)
+    }
+
+    // the statements in order, those recovered from as error ;
+    let mut statements = Vec::<String>::new();
+    let tree = recovered.value.unwrap();
+    let mut list = &tree.children()[0];
+    while !list.children().is_empty()
+    {
+        statements.push(list.children()[1].lexemes().join(" "));
+        list = &list.children()[0];
+    }
+    statements.reverse();
+    assert_eq!(statements, vec!["id = num ;", " ;", "id = num ;", " ;"]);
+
+    // without recovery the first error is the result, and no action runs past it
+    assert!(matches!(parser.parse(String::from(program)), Err(Error::Parse { .. })));
+    struct Lexemes(Vec<String>);
+    impl Actions<()> for Lexemes
+    {
+        fn token(&mut self, token: &Token) { self.0.push(token.symbol.label.clone()); }
+        fn reduce(&mut self, _lhs: &Symbol, _rhs_id: u32, _children: Vec<()>, _span: Span) { }
+    }
+    let mut lexemes = Lexemes(vec![]);
+    assert!(parser.parse_with(Token::split_whitespace(program), &mut lexemes).is_err());
+    assert_eq!(lexemes.0.join(" "), "id = num ; id =");
+
+    // an error no state can shift error for ends the parse
+    let parser = LRParser::new(Grammar::from_file("data/bnf").unwrap(), Mode::SLR).unwrap();
+    let recovered = parser.parse_recovering(Token::split_whitespace("a b b $ d c"));
+    assert_eq!((recovered.value, recovered.errors.len()), (None, 1));
+
+    // a token labelled with the start symbol is an error like any other, not an accept
+    let recovered = parser.parse_recovering(Token::split_whitespace("Start"));
+    assert_eq!(recovered.value, None);
+    assert!(matches!(&recovered.errors[..], [Error::Parse { found: Some(found), .. }] if found.label == "Start"));
+    assert!(matches!(parser.parse(String::from("Start")), Err(Error::Parse { .. })));
+}
+
+#[test]
+fn test_conflict_counterexample()
+{
+    let grammar = Grammar::from_file("data/ambiguous").unwrap();
+    let conflicts = match LRParser::new(grammar, Mode::LALR)
+    {
+        Err(Error::Conflicts(conflicts)) => conflicts,
+        _ => panic!("expected conflicts")
+    };
+    assert_eq!(conflicts.len(), 1);
+
+    // num + num with + next can be reduced first or shifted
+    let sentence = |symbols: &Vec<Symbol>| symbols.iter().map(|symbol| symbol.label.clone()).collect::<Vec<String>>().join(" ");
+    match &conflicts[0]
+    {
+        Conflict::ShiftReduce { items, prefix, input, .. } => {
+            assert_eq!(items.len(), 2);
+            assert!(items[0].starts_with("E -> E + E ~") && items[1].starts_with("E -> E ~ + E"), "{:?}", items);
+            assert_eq!((sentence(prefix), sentence(input)), (String::from("E + E"), String::from("num + num")));
+        },
+        conflict => panic!("unexpected conflict {}", conflict)
+    }
+    assert!(conflicts[0].to_string().ends_with("\n  Example: E + E ~ +\n  Input:   num + num ~ +"));
+}
@@ -0,0 +1,90 @@
+use crate::symbol::Symbol;
+use crate::token::{Span, Token};
+use crate::actions::Actions;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTree
+{
+    // a nonterminal together with the production (rhs_id) it was expanded by
+    Node
+    {
+        symbol: Symbol,
+        rhs_id: u32,
+        children: Vec<ParseTree>,
+        span: Span
+    },
+    // a terminal together with the text it was read from
+    Leaf
+    {
+        symbol: Symbol,
+        lexeme: String,
+        span: Span
+    }
+}
+
+impl ParseTree
+{
+    pub fn symbol(&self) -> &Symbol
+    {
+        match self
+        {
+            ParseTree::Node { symbol, .. } => symbol,
+            ParseTree::Leaf { symbol, .. } => symbol
+        }
+    }
+
+    pub fn span(&self) -> Span
+    {
+        match self
+        {
+            ParseTree::Node { span, .. } => *span,
+            ParseTree::Leaf { span, .. } => *span
+        }
+    }
+
+    pub fn children(&self) -> &[ParseTree]
+    {
+        match self
+        {
+            ParseTree::Node { children, .. } => children,
+            ParseTree::Leaf { .. } => &[]
+        }
+    }
+
+    // the lexemes of all leaves, left to right
+    pub fn lexemes(&self) -> Vec<&str>
+    {
+        match self
+        {
+            ParseTree::Node { children, .. } => children.iter().flat_map(|child| child.lexemes()).collect(),
+            ParseTree::Leaf { lexeme, .. } => vec![&lexeme[..]]
+        }
+    }
+}
+
+// Actions building the concrete syntax tree.
+pub struct TreeBuilder;
+
+impl Actions<ParseTree> for TreeBuilder
+{
+    fn token(&mut self, token: &Token) -> ParseTree
+    {
+        ParseTree::Leaf
+        {
+            symbol: token.symbol.clone(),
+            lexeme: token.lexeme.clone(),
+            span: token.span
+        }
+    }
+
+    fn reduce(&mut self, lhs: &Symbol, rhs_id: u32, children: Vec<ParseTree>, span: Span) -> ParseTree
+    {
+        ParseTree::Node
+        {
+            symbol: lhs.clone(),
+            rhs_id,
+            children,
+            span
+        }
+    }
+}
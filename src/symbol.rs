@@ -15,6 +15,20 @@ impl Display for Symbol
     }
 }
 
+impl Symbol
+{
+    // the reserved terminal of yacc-style error recovery: productions using it match input
+    // that could not be parsed, so lexers must not produce it
+    pub fn error() -> Symbol
+    {
+        Symbol
+        {
+            label: String::from("error"),
+            terminal: true
+        }
+    }
+}
+
 impl From<String> for Symbol
 {
     fn from(text: String) -> Symbol
@@ -0,0 +1,763 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use crate::symbol::Symbol;
+use crate::grammar::{Grammar, Helper};
+use crate::lr_parser::{Action, LRParser, Mode};
+use crate::ll_parser::LLParser;
+use crate::error::Error;
+
+// Saved parse tables, so a parser can be loaded for its grammar without building the tables again.
+// Both formats hold the format version, the parser kind, the fingerprint of the grammar the tables
+// were built from, its productions in declaration order, and the table entries. A loaded parser
+// reduces by the saved productions, which the grammar given on loading must fingerprint the same as.
+
+pub const TABLES_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 4] = b"CUPT";
+const TEXT_HEADER: &str = "compiler_utils parse tables";
+// in the order of their binary codes
+const MODES: [Mode; 4] = [Mode::LR0, Mode::SLR, Mode::LR1, Mode::LALR];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind
+{
+    LR(Mode),
+    LL(usize)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Entry
+{
+    // (state, next symbol, None at end of input) -> action
+    LR(u32, Option<Symbol>, Action),
+    // (lhs, lookahead string) -> rhs_id
+    LL(Symbol, Vec<Symbol>, u32)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tables
+{
+    kind: Kind,
+    fingerprint: u64,
+    // (lhs, rhs_id, rhs); binary reductions refer to them by index
+    productions: Vec<(Symbol, u32, Vec<Symbol>)>,
+    entries: Vec<Entry>
+}
+
+fn tables_error(message: &str) -> Error
+{
+    Error::Tables(message.to_string())
+}
+
+impl Grammar
+{
+    // FNV-1a over the start symbol, productions, helpers and precedence, which is all that the
+    // tables depend on; stable across runs and builds, unlike the std hashers
+    pub fn fingerprint(&self) -> u64
+    {
+        let mut lines = Vec::<String>::new();
+        lines.push(format!("start {}", quote(&self.start)));
+        for (lhs, prod_list) in &self.productions
+        {
+            for (rhs_id, rhs) in prod_list.iter().enumerate()
+            {
+                lines.push(format!("production {} {} -> {}", quote(lhs), rhs_id, quote_all(rhs)));
+            }
+        }
+        for (symbol, helper) in &self.helpers
+        {
+            lines.push(match helper
+            {
+                Helper::Repeat { element, at_least_one } => format!("helper {} repeat {} {}", quote(symbol), quote(element), at_least_one),
+                Helper::Optional { element } => format!("helper {} optional {}", quote(symbol), quote(element)),
+                Helper::Group => format!("helper {} group", quote(symbol))
+            });
+        }
+        for (symbol, (level, associativity)) in &self.precedence
+        {
+            lines.push(format!("precedence {} {} {:?}", quote(symbol), level, associativity));
+        }
+        for ((lhs, rhs_id), symbol) in &self.production_precedence
+        {
+            lines.push(format!("prec {} {} {}", quote(lhs), rhs_id, quote(symbol)));
+        }
+        lines.sort();
+
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in lines.join("\n").bytes()
+        {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+
+    pub(crate) fn production_list(&self) -> Vec<(Symbol, u32, Vec<Symbol>)>
+    {
+        self.rule_order()
+            .into_iter()
+            .flat_map(|lhs| self.productions[&lhs]
+                .iter()
+                .enumerate()
+                .map(|(rhs_id, rhs)| (lhs.clone(), rhs_id as u32, rhs.clone()))
+                .collect::<Vec<(Symbol, u32, Vec<Symbol>)>>())
+            .collect()
+    }
+}
+
+impl LRParser
+{
+    pub fn save(&self) -> Vec<u8>
+    {
+        self.tables().to_bytes()
+    }
+
+    pub fn save_text(&self) -> String
+    {
+        self.tables().to_text()
+    }
+
+    // a parser for grammar from tables saved from one, failing if they are of another version or grammar
+    pub fn load(grammar: Grammar, data: &[u8]) -> Result<LRParser, Error>
+    {
+        LRParser::from_tables(grammar, Tables::from_bytes(data)?)
+    }
+
+    pub fn load_text(grammar: Grammar, text: &str) -> Result<LRParser, Error>
+    {
+        LRParser::from_tables(grammar, Tables::from_text(text)?)
+    }
+
+    fn tables(&self) -> Tables
+    {
+        let mut entries = self.parse_table
+            .iter()
+            .map(|((state, symbol), action)| Entry::LR(*state, symbol.clone(), action.clone()))
+            .collect::<Vec<Entry>>();
+        entries.sort_by_key(|entry| match entry
+        {
+            Entry::LR(state, symbol, _) => (*state, symbol.clone()),
+            Entry::LL(..) => unreachable!()
+        });
+
+        Tables
+        {
+            kind: Kind::LR(self.mode),
+            fingerprint: self.grammar.fingerprint(),
+            productions: self.grammar.production_list(),
+            entries
+        }
+    }
+
+    fn from_tables(grammar: Grammar, tables: Tables) -> Result<LRParser, Error>
+    {
+        let mode = match tables.kind
+        {
+            Kind::LR(mode) => mode,
+            Kind::LL(k) => return Err(Error::Tables(format!("these are LL({}) tables, not LR tables", k)))
+        };
+        let grammar = tables.grammar(grammar)?;
+
+        let parse_table = tables.entries
+            .into_iter()
+            .filter_map(|entry| match entry
+            {
+                Entry::LR(state, symbol, action) => Some(((state, symbol), action)),
+                Entry::LL(..) => None
+            })
+            .collect::<HashMap<(u32, Option<Symbol>), Action>>();
+
+        Ok(LRParser
+        {
+            grammar,
+            parse_table,
+            mode,
+            trace: false
+        })
+    }
+}
+
+impl LLParser
+{
+    pub fn save(&self) -> Vec<u8>
+    {
+        self.tables().to_bytes()
+    }
+
+    pub fn save_text(&self) -> String
+    {
+        self.tables().to_text()
+    }
+
+    // a parser for grammar from tables saved from one, failing if they are of another version or grammar
+    pub fn load(grammar: Grammar, data: &[u8]) -> Result<LLParser, Error>
+    {
+        LLParser::from_tables(grammar, Tables::from_bytes(data)?)
+    }
+
+    pub fn load_text(grammar: Grammar, text: &str) -> Result<LLParser, Error>
+    {
+        LLParser::from_tables(grammar, Tables::from_text(text)?)
+    }
+
+    fn tables(&self) -> Tables
+    {
+        let order = self.grammar.rule_order();
+        let mut entries = self.parse_table
+            .iter()
+            .map(|((lhs, lookahead), rhs_id)| Entry::LL(lhs.clone(), lookahead.clone(), *rhs_id))
+            .collect::<Vec<Entry>>();
+        entries.sort_by_key(|entry| match entry
+        {
+            Entry::LL(lhs, lookahead, _) => (order.iter().position(|other| other == lhs), lookahead.clone()),
+            Entry::LR(..) => unreachable!()
+        });
+
+        Tables
+        {
+            kind: Kind::LL(self.k),
+            fingerprint: self.grammar.fingerprint(),
+            productions: self.grammar.production_list(),
+            entries
+        }
+    }
+
+    fn from_tables(grammar: Grammar, tables: Tables) -> Result<LLParser, Error>
+    {
+        let k = match tables.kind
+        {
+            Kind::LL(k) => k,
+            Kind::LR(mode) => return Err(Error::Tables(format!("these are {:?} tables, not LL tables", mode)))
+        };
+        // the tables were built for the grammar as the parser rewrites it
+        let grammar = tables.grammar(grammar.right_recursive())?;
+
+        let parse_table = tables.entries
+            .into_iter()
+            .filter_map(|entry| match entry
+            {
+                Entry::LL(lhs, lookahead, rhs_id) => Some(((lhs, lookahead), rhs_id)),
+                Entry::LR(..) => None
+            })
+            .collect::<HashMap<(Symbol, Vec<Symbol>), u32>>();
+
+        Ok(LLParser
+        {
+            grammar,
+            parse_table,
+            k,
+            trace: false
+        })
+    }
+}
+
+// Symbols in the text format: terminals in single quotes, non-terminals in double quotes,
+// with backslash escapes, so that any label reads back as the same symbol.
+fn quote(symbol: &Symbol) -> String
+{
+    let delimiter = if symbol.terminal { '\'' } else { '"' };
+    let mut out = String::from(delimiter);
+    for c in symbol.label.chars()
+    {
+        match c
+        {
+            '\\' | '\'' | '"' => { out.push('\\'); out.push(c); },
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c)
+        }
+    }
+    out.push(delimiter);
+    out
+}
+
+fn quote_all(symbols: &[Symbol]) -> String
+{
+    symbols.iter().map(quote).collect::<Vec<String>>().join(" ")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Word
+{
+    Bare(String),
+    Symbol(Symbol)
+}
+
+fn split_words(line: &str) -> Result<Vec<Word>, Error>
+{
+    let mut words = Vec::<Word>::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek()
+    {
+        if c.is_whitespace()
+        {
+            chars.next();
+        }
+        else if c == '\'' || c == '"'
+        {
+            chars.next();
+            let mut label = String::new();
+            loop
+            {
+                match chars.next()
+                {
+                    Some('\\') => match chars.next()
+                    {
+                        Some('n') => label.push('\n'),
+                        Some(escaped) => label.push(escaped),
+                        None => return Err(tables_error("unterminated symbol"))
+                    },
+                    Some(next) if next == c => break,
+                    Some(next) => label.push(next),
+                    None => return Err(tables_error("unterminated symbol"))
+                }
+            }
+            words.push(Word::Symbol(Symbol { label, terminal: c == '\'' }));
+        }
+        else
+        {
+            let mut word = String::new();
+            while let Some(&next) = chars.peek().filter(|next| !next.is_whitespace())
+            {
+                word.push(next);
+                chars.next();
+            }
+            words.push(Word::Bare(word));
+        }
+    }
+    Ok(words)
+}
+
+fn mode_name(mode: Mode) -> &'static str
+{
+    match mode
+    {
+        Mode::LR0 => "LR0",
+        Mode::SLR => "SLR",
+        Mode::LR1 => "LR1",
+        Mode::LALR => "LALR"
+    }
+}
+
+fn mode_from_name(name: &str) -> Option<Mode>
+{
+    MODES.iter().copied().find(|mode| mode_name(*mode) == name)
+}
+
+impl Tables
+{
+    // grammar with the saved productions, after checking that it is the grammar the tables were built
+    // from and that every entry refers to a saved production and to a state of the tables
+    fn grammar(&self, mut grammar: Grammar) -> Result<Grammar, Error>
+    {
+        if self.fingerprint != grammar.fingerprint()
+        {
+            return Err(Error::Tables(format!("the tables were built from a different grammar (fingerprint {:016x}, the grammar's is {:016x})",
+                self.fingerprint, grammar.fingerprint())));
+        }
+        let mut productions = HashMap::<Symbol, Vec<Vec<Symbol>>>::new();
+        for (lhs, rhs_id, rhs) in &self.productions
+        {
+            let prod_list = productions.entry(lhs.clone()).or_default();
+            if *rhs_id as usize != prod_list.len()
+            {
+                return Err(Error::Tables(format!("production {} {} is out of order", quote(lhs), rhs_id)));
+            }
+            prod_list.push(rhs.clone());
+        }
+        grammar.productions = productions;
+        if grammar.fingerprint() != self.fingerprint
+        {
+            return Err(tables_error("the saved productions are not those of the grammar"));
+        }
+
+        let states = self.entries
+            .iter()
+            .filter_map(|entry| match entry
+            {
+                Entry::LR(state, _, _) => Some(*state),
+                Entry::LL(..) => None
+            })
+            .collect::<HashSet<u32>>();
+        for entry in &self.entries
+        {
+            match (self.kind, entry)
+            {
+                (Kind::LR(_), Entry::LR(state, _, Action::Shift(next))) if !states.contains(next) => {
+                    return Err(Error::Tables(format!("state {} goes to state {}, which the tables do not have", state, next)));
+                },
+                (Kind::LR(_), Entry::LR(_, _, Action::Reduce((lhs, rhs_id)))) | (Kind::LL(_), Entry::LL(lhs, _, rhs_id))
+                    if grammar.get_rhs(lhs, *rhs_id).is_none() => {
+                    return Err(Error::Tables(format!("there is no production {} {}", quote(lhs), rhs_id)));
+                },
+                (Kind::LR(_), Entry::LR(..)) | (Kind::LL(_), Entry::LL(..)) => {},
+                _ => return Err(tables_error("LR and LL entries are mixed"))
+            }
+        }
+        Ok(grammar)
+    }
+
+    fn production_index(&self, lhs: &Symbol, rhs_id: u32) -> u32
+    {
+        self.productions.iter().position(|(other, other_id, _)| other == lhs && *other_id == rhs_id).unwrap() as u32
+    }
+
+    // Little endian: "CUPT", version u32, kind u8 (0-3 the LR modes, 4 LL), k u32, fingerprint u64,
+    // then counted lists of symbols (u8 terminal, u32 length, UTF-8), productions (u32 lhs, u32 length,
+    // u32 symbols) and entries. LR entries are u32 state, u32 symbol (0 for end of input, otherwise
+    // index + 1), u8 action (0 shift, 1 reduce, 2 accept), u32 state or production; LL entries are
+    // u32 lhs, u32 length, u32 symbols, u32 rhs_id.
+    fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut symbols = Vec::<Symbol>::new();
+        let mut index = HashMap::<Symbol, u32>::new();
+        let mut intern = |symbol: &Symbol| *index.entry(symbol.clone()).or_insert_with(|| {
+            symbols.push(symbol.clone());
+            symbols.len() as u32 - 1
+        });
+
+        let mut body = Vec::<u8>::new();
+        let put = |out: &mut Vec<u8>, value: u32| out.extend_from_slice(&value.to_le_bytes());
+
+        put(&mut body, self.productions.len() as u32);
+        for (lhs, _, rhs) in &self.productions
+        {
+            put(&mut body, intern(lhs));
+            put(&mut body, rhs.len() as u32);
+            for symbol in rhs
+            {
+                put(&mut body, intern(symbol));
+            }
+        }
+        put(&mut body, self.entries.len() as u32);
+        for entry in &self.entries
+        {
+            match entry
+            {
+                Entry::LR(state, symbol, action) => {
+                    put(&mut body, *state);
+                    put(&mut body, symbol.as_ref().map_or(0, |symbol| intern(symbol) + 1));
+                    let (tag, value) = match action
+                    {
+                        Action::Shift(state) => (0, *state),
+                        Action::Reduce((lhs, rhs_id)) => (1, self.production_index(lhs, *rhs_id)),
+                        Action::Accept => (2, 0)
+                    };
+                    body.push(tag);
+                    put(&mut body, value);
+                },
+                Entry::LL(lhs, lookahead, rhs_id) => {
+                    put(&mut body, intern(lhs));
+                    put(&mut body, lookahead.len() as u32);
+                    for symbol in lookahead
+                    {
+                        put(&mut body, intern(symbol));
+                    }
+                    put(&mut body, *rhs_id);
+                }
+            }
+        }
+
+        let mut out = MAGIC.to_vec();
+        put(&mut out, TABLES_VERSION);
+        let (kind, k) = match self.kind
+        {
+            Kind::LR(mode) => (MODES.iter().position(|other| *other == mode).unwrap() as u8, 0),
+            Kind::LL(k) => (4, k as u32)
+        };
+        out.push(kind);
+        put(&mut out, k);
+        out.extend_from_slice(&self.fingerprint.to_le_bytes());
+        put(&mut out, symbols.len() as u32);
+        for symbol in &symbols
+        {
+            out.push(symbol.terminal as u8);
+            put(&mut out, symbol.label.len() as u32);
+            out.extend_from_slice(symbol.label.as_bytes());
+        }
+        out.extend(body);
+        out
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Tables, Error>
+    {
+        let mut bytes = Bytes { data, position: 0 };
+        if bytes.take(4)? != MAGIC
+        {
+            return Err(tables_error("not a parse table file"));
+        }
+        let version = bytes.u32()?;
+        if version != TABLES_VERSION
+        {
+            return Err(Error::Tables(format!("the tables are version {}, expected {}", version, TABLES_VERSION)));
+        }
+        let kind = match (bytes.u8()?, bytes.u32()?)
+        {
+            (kind @ 0..=3, _) => Kind::LR(MODES[kind as usize]),
+            (4, k) => Kind::LL(k as usize),
+            _ => return Err(tables_error("unknown parser kind"))
+        };
+        let fingerprint = bytes.u64()?;
+
+        let mut symbols = Vec::<Symbol>::new();
+        for _ in 0..bytes.u32()?
+        {
+            let terminal = bytes.u8()? != 0;
+            let length = bytes.u32()? as usize;
+            let label = String::from_utf8(bytes.take(length)?.to_vec()).map_err(|_| tables_error("a symbol is not UTF-8"))?;
+            symbols.push(Symbol { label, terminal });
+        }
+        let symbol = |index: u32| symbols.get(index as usize).cloned().ok_or_else(|| tables_error("symbol index out of range"));
+
+        let mut productions = Vec::<(Symbol, u32, Vec<Symbol>)>::new();
+        for _ in 0..bytes.u32()?
+        {
+            let lhs = symbol(bytes.u32()?)?;
+            let rhs = (0..bytes.u32()?).map(|_| symbol(bytes.u32()?)).collect::<Result<Vec<Symbol>, Error>>()?;
+            let rhs_id = productions.iter().filter(|(other, _, _)| *other == lhs).count() as u32;
+            productions.push((lhs, rhs_id, rhs));
+        }
+
+        let mut entries = Vec::<Entry>::new();
+        for _ in 0..bytes.u32()?
+        {
+            entries.push(match kind
+            {
+                Kind::LR(_) => {
+                    let state = bytes.u32()?;
+                    let next = match bytes.u32()?
+                    {
+                        0 => None,
+                        index => Some(symbol(index - 1)?)
+                    };
+                    let action = match (bytes.u8()?, bytes.u32()?)
+                    {
+                        (0, state) => Action::Shift(state),
+                        (1, index) => {
+                            let (lhs, rhs_id, _) = productions.get(index as usize).ok_or_else(|| tables_error("production index out of range"))?;
+                            Action::Reduce((lhs.clone(), *rhs_id))
+                        },
+                        (2, _) => Action::Accept,
+                        _ => return Err(tables_error("unknown action"))
+                    };
+                    Entry::LR(state, next, action)
+                },
+                Kind::LL(_) => {
+                    let lhs = symbol(bytes.u32()?)?;
+                    let lookahead = (0..bytes.u32()?).map(|_| symbol(bytes.u32()?)).collect::<Result<Vec<Symbol>, Error>>()?;
+                    Entry::LL(lhs, lookahead, bytes.u32()?)
+                }
+            });
+        }
+        if bytes.position != data.len()
+        {
+            return Err(tables_error("trailing data after the tables"));
+        }
+
+        Ok(Tables
+        {
+            kind,
+            fingerprint,
+            productions,
+            entries
+        })
+    }
+
+    // One item per line: the header, version, parser and fingerprint, then
+    //     production "Expr" 1 -> "Expr" '+' "Term"
+    //     action 4 '+' shift 7 | action 9 end reduce "Expr" 1 | action 1 "Program" accept
+    //     predict "Expr" 'NUM' '+' -> 0
+    fn to_text(&self) -> String
+    {
+        let mut lines = vec![String::from(TEXT_HEADER), format!("version {}", TABLES_VERSION)];
+        lines.push(match self.kind
+        {
+            Kind::LR(mode) => format!("parser {}", mode_name(mode)),
+            Kind::LL(k) => format!("parser LL {}", k)
+        });
+        lines.push(format!("fingerprint {:016x}", self.fingerprint));
+        for (lhs, rhs_id, rhs) in &self.productions
+        {
+            match rhs.is_empty()
+            {
+                true => lines.push(format!("production {} {} ->", quote(lhs), rhs_id)),
+                false => lines.push(format!("production {} {} -> {}", quote(lhs), rhs_id, quote_all(rhs)))
+            }
+        }
+        for entry in &self.entries
+        {
+            lines.push(match entry
+            {
+                Entry::LR(state, symbol, action) => {
+                    let symbol = symbol.as_ref().map_or(String::from("end"), quote);
+                    match action
+                    {
+                        Action::Shift(next) => format!("action {} {} shift {}", state, symbol, next),
+                        Action::Reduce((lhs, rhs_id)) => format!("action {} {} reduce {} {}", state, symbol, quote(lhs), rhs_id),
+                        Action::Accept => format!("action {} {} accept", state, symbol)
+                    }
+                },
+                Entry::LL(lhs, lookahead, rhs_id) if lookahead.is_empty() => format!("predict {} -> {}", quote(lhs), rhs_id),
+                Entry::LL(lhs, lookahead, rhs_id) => format!("predict {} {} -> {}", quote(lhs), quote_all(lookahead), rhs_id)
+            });
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    fn from_text(text: &str) -> Result<Tables, Error>
+    {
+        let mut lines = text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        if lines.next().map(|(_, line)| line.trim()) != Some(TEXT_HEADER)
+        {
+            return Err(tables_error("not a parse table file"));
+        }
+
+        let mut kind = None;
+        let mut fingerprint = None;
+        let mut productions = Vec::<(Symbol, u32, Vec<Symbol>)>::new();
+        let mut entries = Vec::<Entry>::new();
+        for (number, line) in lines
+        {
+            let bad_line = || Error::Tables(format!("line {} is malformed: {}", number + 1, line));
+            let words = split_words(line)?;
+            let bare = |index: usize| match words.get(index)
+            {
+                Some(Word::Bare(word)) => Some(word.as_str()),
+                _ => None
+            };
+            let number_at = |index: usize| bare(index).and_then(|word| word.parse::<u32>().ok()).ok_or_else(bad_line);
+            let symbol_at = |index: usize| match words.get(index)
+            {
+                Some(Word::Symbol(symbol)) => Ok(symbol.clone()),
+                _ => Err(bad_line())
+            };
+            let symbols_between = |from: usize, to: usize| (from..to).map(symbol_at).collect::<Result<Vec<Symbol>, Error>>();
+
+            match bare(0)
+            {
+                Some("version") => {
+                    let version = number_at(1)?;
+                    if version != TABLES_VERSION
+                    {
+                        return Err(Error::Tables(format!("the tables are version {}, expected {}", version, TABLES_VERSION)));
+                    }
+                },
+                Some("parser") => kind = Some(match (bare(1), bare(2))
+                {
+                    (Some("LL"), Some(_)) => Kind::LL(number_at(2)? as usize),
+                    (Some(name), None) => Kind::LR(mode_from_name(name).ok_or_else(bad_line)?),
+                    _ => return Err(bad_line())
+                }),
+                Some("fingerprint") => fingerprint = Some(bare(1).and_then(|word| u64::from_str_radix(word, 16).ok()).ok_or_else(bad_line)?),
+                Some("production") if bare(3) == Some("->") => productions.push((symbol_at(1)?, number_at(2)?, symbols_between(4, words.len())?)),
+                Some("action") => {
+                    let next = match bare(2)
+                    {
+                        Some("end") => None,
+                        _ => Some(symbol_at(2)?)
+                    };
+                    let action = match bare(3)
+                    {
+                        Some("shift") => Action::Shift(number_at(4)?),
+                        Some("reduce") => Action::Reduce((symbol_at(4)?, number_at(5)?)),
+                        Some("accept") => Action::Accept,
+                        _ => return Err(bad_line())
+                    };
+                    entries.push(Entry::LR(number_at(1)?, next, action));
+                },
+                Some("predict") if words.len() >= 3 && bare(words.len() - 2) == Some("->") => {
+                    entries.push(Entry::LL(symbol_at(1)?, symbols_between(2, words.len() - 2)?, number_at(words.len() - 1)?));
+                },
+                _ => return Err(bad_line())
+            }
+        }
+
+        Ok(Tables
+        {
+            kind: kind.ok_or_else(|| tables_error("the parser kind is missing"))?,
+            fingerprint: fingerprint.ok_or_else(|| tables_error("the fingerprint is missing"))?,
+            productions,
+            entries
+        })
+    }
+}
+
+struct Bytes<'a>
+{
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> Bytes<'a>
+{
+    fn take(&mut self, length: usize) -> Result<&'a [u8], Error>
+    {
+        let end = self.position.checked_add(length).filter(|end| *end <= self.data.len()).ok_or_else(|| tables_error("the tables are truncated"))?;
+        let out = &self.data[self.position..end];
+        self.position = end;
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error>
+    {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Error>
+    {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error>
+    {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[test]
+fn test_save_and_load()
+{
+    let bnf = Grammar::from_file("data/bnf").unwrap();
+    let parser = LRParser::new(bnf.clone(), Mode::LALR).unwrap();
+    let loaded = LRParser::load(bnf.clone(), &parser.save()).unwrap();
+    assert_eq!(loaded.parse_table, parser.parse_table);
+    assert_eq!(loaded.parse(String::from("a b b d c $")).unwrap(), parser.parse(String::from("a b b d c $")).unwrap());
+
+    let grammar = Grammar::from_file("data/calc").unwrap();
+    let parser = LRParser::new(grammar.clone(), Mode::LALR).unwrap();
+    let text = parser.save_text();
+    assert!(text.starts_with("compiler_utils parse tables\nversion 1\nparser LALR\nfingerprint "));
+    assert!(text.contains("\nproduction \"Expr\" 0 -> \"Expr\" '+' \"Term\"\n"));
+    assert_eq!(LRParser::load_text(grammar.clone(), &text).unwrap().parse_table, parser.parse_table);
+
+    let parser = LLParser::with_lookahead(Grammar::from_file("data/ll3").unwrap(), 3).unwrap();
+    let loaded = LLParser::load(Grammar::from_file("data/ll3").unwrap(), &parser.save()).unwrap();
+    assert_eq!((loaded.k, &loaded.parse_table), (3, &parser.parse_table));
+    let loaded = LLParser::load_text(Grammar::from_file("data/ll3").unwrap(), &parser.save_text()).unwrap();
+    assert_eq!(loaded.parse_table, parser.parse_table);
+
+    // tables of another grammar, version or kind are refused
+    let saved = LRParser::new(grammar.clone(), Mode::LALR).unwrap().save();
+    let other = "%token NUM ; Program -> Expr '$' ; Expr -> Expr '-' NUM | NUM ;".parse::<Grammar>().unwrap();
+    assert!(matches!(LRParser::load(other, &saved), Err(Error::Tables(message)) if message.contains("different grammar")));
+    let mut future = saved.clone();
+    future[4] = 2;
+    assert_eq!(LRParser::load(grammar.clone(), &future).err(), Some(Error::Tables(String::from("the tables are version 2, expected 1"))));
+    assert!(LLParser::load(grammar.clone(), &saved).is_err());
+    assert!(LRParser::load(grammar, &saved[..saved.len() - 1]).is_err());
+
+    // and so are tables whose entries or productions were edited
+    let text = LRParser::new(bnf.clone(), Mode::LALR).unwrap().save_text();
+    let refused = |text: &str| match LRParser::load_text(bnf.clone(), text)
+    {
+        Err(Error::Tables(message)) => message,
+        other => panic!("{:?}", other.map(|parser| parser.parse_table))
+    };
+    assert_eq!(refused(&text.replacen("reduce \"B\" 1", "reduce \"B\" 7", 1)), "there is no production \"B\" 7");
+    assert_eq!(refused(&text.replace("action 5 'b' shift 5", "action 5 'b' shift 15")), "state 5 goes to state 15, which the tables do not have");
+    assert_eq!(refused(&text.replace("production \"Q\" 0 -> 'q'", "production \"Q\" 0 -> 'c'")), "the saved productions are not those of the grammar");
+    let mut saved = LLParser::with_lookahead(Grammar::from_file("data/ll3").unwrap(), 3).unwrap().save();
+    let last = saved.len() - 4;
+    saved[last] = 7;
+    assert!(matches!(LLParser::load(Grammar::from_file("data/ll3").unwrap(), &saved), Err(Error::Tables(message)) if message == "there is no production \"Stmt\" 7"));
+}
@@ -0,0 +1,148 @@
+use crate::symbol::Symbol;
+
+// byte offsets into the input, end exclusive, plus the 1-based line and column (in characters) of the start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span
+{
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize
+}
+
+impl Span
+{
+    // the empty span at the beginning of the input
+    pub fn origin() -> Span
+    {
+        Span
+        {
+            start: 0,
+            end: 0,
+            line: 1,
+            column: 1
+        }
+    }
+
+    // the span of the given text when it starts where this span does
+    pub fn over(&self, text: &str) -> Span
+    {
+        Span
+        {
+            end: self.start + text.len(),
+            ..*self
+        }
+    }
+
+    // the empty span just past the given text when it starts where this span does
+    pub fn after(&self, text: &str) -> Span
+    {
+        let mut line = self.line;
+        let mut column = self.column;
+        for c in text.chars()
+        {
+            if c == '\n'
+            {
+                line += 1;
+                column = 1;
+            }
+            else
+            {
+                column += 1;
+            }
+        }
+
+        Span
+        {
+            start: self.start + text.len(),
+            end: self.start + text.len(),
+            line,
+            column
+        }
+    }
+
+    // the empty span where this one begins
+    pub fn collapsed(&self) -> Span
+    {
+        Span
+        {
+            end: self.start,
+            ..*self
+        }
+    }
+
+    // from the start of this span to the end of the other
+    pub fn to(&self, other: &Span) -> Span
+    {
+        Span
+        {
+            end: other.end,
+            ..*self
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token
+{
+    pub symbol: Symbol,
+    pub lexeme: String,
+    pub span: Span
+}
+
+impl Token
+{
+    // the original tokenization: every whitespace separated word is a symbol named after itself
+    pub fn split_whitespace(program: &str) -> Vec<Token>
+    {
+        let mut tokens = Vec::<Token>::new();
+        let mut position = Span::origin();
+        let mut start: Option<Span> = None;
+
+        for (index, c) in program.char_indices().chain(std::iter::once((program.len(), ' ')))
+        {
+            if c.is_whitespace()
+            {
+                if let Some(start) = start.take()
+                {
+                    let lexeme = program[start.start..index].to_string();
+                    tokens.push(Token
+                    {
+                        symbol: Symbol::from(lexeme.clone()),
+                        span: start.over(&lexeme),
+                        lexeme
+                    });
+                }
+            }
+            else if start.is_none()
+            {
+                start = Some(position);
+            }
+
+            if index < program.len()
+            {
+                position = position.after(&program[index..index + c.len_utf8()]);
+            }
+        }
+
+        tokens
+    }
+
+    // the empty span just past the token
+    pub fn end(&self) -> Span
+    {
+        self.span.after(&self.lexeme)
+    }
+}
+
+#[test]
+fn test_split_whitespace()
+{
+    let tokens = Token::split_whitespace(" a  bc\td\n  ef ");
+
+    assert_eq!(tokens.iter().map(|token| &token.lexeme[..]).collect::<Vec<&str>>(), vec!["a", "bc", "d", "ef"]);
+    assert_eq!(tokens[1].span, Span { start: 4, end: 6, line: 1, column: 5 });
+    assert_eq!(tokens[2].span, Span { start: 7, end: 8, line: 1, column: 8 });
+    assert_eq!(tokens[3].span, Span { start: 11, end: 13, line: 2, column: 3 });
+    assert_eq!(tokens[3].end(), Span { start: 13, end: 13, line: 2, column: 5 });
+}
@@ -0,0 +1,641 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::symbol::Symbol;
+use crate::grammar::{Grammar, Helper};
+use crate::parse_tree::ParseTree;
+use crate::error::Error;
+use crate::token::Span;
+
+// How the children of a rewritten production make up trees of the original grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Template
+{
+    // the rebuilt child at this index of the rewritten production
+    Child(usize),
+    // a node for production rhs_id of lhs in the original grammar
+    Node
+    {
+        lhs: Symbol,
+        rhs_id: u32,
+        children: Vec<Template>
+    },
+    // the tree built so far, which a tail of removed left recursion extends
+    Seed,
+    // builds seed, then hands it on to the tail nonterminal at index tail
+    Continue
+    {
+        seed: Box<Template>,
+        tail: usize
+    },
+    // hands the children before index tail, inherited ones included, to the left factored
+    // nonterminal at tail, whose templates count them as their first children
+    Pass
+    {
+        tail: usize
+    }
+}
+
+impl Template
+{
+    // a production that was not rewritten
+    pub fn identity(lhs: &Symbol, rhs_id: u32, len: usize) -> Template
+    {
+        Template::Node
+        {
+            lhs: lhs.clone(),
+            rhs_id,
+            children: (0..len).map(Template::Child).collect()
+        }
+    }
+
+    // Child(index) becomes replacement, which spans width children; later children shift to match
+    pub fn substitute(&self, index: usize, replacement: &Template, width: usize) -> Template
+    {
+        match self
+        {
+            Template::Child(child) if *child == index => replacement.shifted(index),
+            Template::Child(child) if *child > index => Template::Child(child + width - 1),
+            Template::Child(child) => Template::Child(*child),
+            Template::Node { lhs, rhs_id, children } => Template::Node
+            {
+                lhs: lhs.clone(),
+                rhs_id: *rhs_id,
+                children: children.iter().map(|child| child.substitute(index, replacement, width)).collect()
+            },
+            Template::Seed => Template::Seed,
+            Template::Continue { seed, tail } => Template::Continue
+            {
+                seed: Box::new(seed.substitute(index, replacement, width)),
+                tail: if *tail > index { tail + width - 1 } else { *tail }
+            },
+            Template::Pass { tail } => Template::Pass
+            {
+                tail: if *tail > index { tail + width - 1 } else { *tail }
+            }
+        }
+    }
+
+    fn shifted(&self, offset: usize) -> Template
+    {
+        match self
+        {
+            Template::Child(child) => Template::Child(child + offset),
+            Template::Node { lhs, rhs_id, children } => Template::Node
+            {
+                lhs: lhs.clone(),
+                rhs_id: *rhs_id,
+                children: children.iter().map(|child| child.shifted(offset)).collect()
+            },
+            Template::Seed => Template::Seed,
+            Template::Continue { seed, tail } => Template::Continue
+            {
+                seed: Box::new(seed.shifted(offset)),
+                tail: tail + offset
+            },
+            Template::Pass { tail } => Template::Pass
+            {
+                tail: tail + offset
+            }
+        }
+    }
+}
+
+// productions under construction, each with the template back to the grammar being transformed
+type Rules = HashMap<Symbol, Vec<(Vec<Symbol>, Template)>>;
+
+// A grammar rewritten by a transformation, together with the way back to the original's trees.
+#[derive(Debug, Clone)]
+pub struct Transformed
+{
+    pub grammar: Grammar,
+    // (lhs, rhs_id) of the rewritten grammar -> the trees of the grammar it was made from
+    origins: HashMap<(Symbol, u32), Template>,
+    // when transformations are chained, the one this grammar was made from
+    previous: Option<Box<Transformed>>
+}
+
+impl Transformed
+{
+    // relative to the grammar this one was made from, which is the previous link when chained
+    pub fn origin(&self, lhs: &Symbol, rhs_id: u32) -> Option<&Template>
+    {
+        self.origins.get(&(lhs.clone(), rhs_id))
+    }
+
+    // turns a tree of the rewritten grammar into the tree the original grammar would have given,
+    // so removed left recursion comes back left associative and factored prefixes go back in place
+    pub fn rebuild(&self, tree: &ParseTree) -> ParseTree
+    {
+        let tree = self.rebuild_one(tree);
+        match &self.previous
+        {
+            Some(previous) => previous.rebuild(&tree),
+            None => tree
+        }
+    }
+
+    // removes left recursion from the rewritten grammar, keeping the way back to the first one
+    pub fn eliminate_left_recursion(self) -> Result<Transformed, Error>
+    {
+        let mut next = self.grammar.eliminate_left_recursion()?;
+        next.previous = Some(Box::new(self));
+        Ok(next)
+    }
+
+    // left factors the rewritten grammar, keeping the way back to the first one
+    pub fn left_factor(self) -> Transformed
+    {
+        let mut next = self.grammar.left_factor();
+        next.previous = Some(Box::new(self));
+        next
+    }
+
+    fn rebuild_one(&self, tree: &ParseTree) -> ParseTree
+    {
+        self.rebuild_with(tree, None, vec![])
+    }
+
+    fn rebuild_with(&self, tree: &ParseTree, mut seed: Option<ParseTree>, inherited: Vec<ParseTree>) -> ParseTree
+    {
+        match tree
+        {
+            ParseTree::Leaf { .. } => tree.clone(),
+            // lists the parsers flattened were never rewritten
+            ParseTree::Node { symbol, rhs_id, children, span } => match self.origin(symbol, *rhs_id)
+            {
+                Some(template) if !self.is_list(symbol) => {
+                    let mut children = children.clone();
+                    children.splice(0..0, inherited);
+                    self.instantiate(template, &children, &mut seed, *span)
+                },
+                _ => ParseTree::Node
+                {
+                    symbol: symbol.clone(),
+                    rhs_id: *rhs_id,
+                    children: children.iter().map(|child| self.rebuild_one(child)).collect(),
+                    span: *span
+                }
+            }
+        }
+    }
+
+    fn instantiate(&self, template: &Template, children: &[ParseTree], seed: &mut Option<ParseTree>, span: Span) -> ParseTree
+    {
+        match template
+        {
+            Template::Child(index) => self.rebuild_one(&children[*index]),
+            Template::Node { lhs, rhs_id, children: parts } => {
+                let rebuilt = parts
+                    .iter()
+                    .map(|part| self.instantiate(part, children, seed, span))
+                    .collect::<Vec<ParseTree>>();
+                let span = match (rebuilt.first(), rebuilt.last())
+                {
+                    (Some(first), Some(last)) => first.span().to(&last.span()),
+                    _ => span.collapsed()
+                };
+                ParseTree::Node
+                {
+                    symbol: lhs.clone(),
+                    rhs_id: *rhs_id,
+                    children: rebuilt,
+                    span
+                }
+            },
+            Template::Seed => seed.take().expect("a tail is always given the tree so far"),
+            Template::Continue { seed: next, tail } => {
+                let next = self.instantiate(next, children, seed, span);
+                self.rebuild_with(&children[*tail], Some(next), vec![])
+            },
+            Template::Pass { tail } => self.rebuild_with(&children[*tail], seed.take(), children[..*tail].to_vec())
+        }
+    }
+
+    fn is_list(&self, symbol: &Symbol) -> bool
+    {
+        matches!(self.grammar.helpers.get(symbol), Some(Helper::Repeat { .. }) | Some(Helper::Optional { .. }))
+    }
+
+    // installs the rules in grammar, dropping whatever substitution or factoring left unreachable
+    fn from_rules(mut grammar: Grammar, rules: Rules, added: Vec<Symbol>) -> Transformed
+    {
+        let mut origins = HashMap::<(Symbol, u32), Template>::new();
+        for (lhs, prod_list) in rules
+        {
+            let mut rhs_list = Vec::<Vec<Symbol>>::new();
+            for (rhs_id, (rhs, template)) in prod_list.into_iter().enumerate()
+            {
+                origins.insert((lhs.clone(), rhs_id as u32), template);
+                rhs_list.push(rhs);
+            }
+            grammar.productions.insert(lhs, rhs_list);
+        }
+        for symbol in added
+        {
+            grammar.nonterminals.insert(symbol.clone());
+            grammar.order.push(symbol);
+        }
+
+        let reachable = grammar.reachable_symbols();
+        grammar.productions.retain(|lhs, _| reachable.contains(lhs));
+        grammar.nonterminals.retain(|symbol| reachable.contains(symbol));
+        grammar.terminals.retain(|symbol| reachable.contains(symbol));
+        grammar.helpers.retain(|symbol, _| reachable.contains(symbol));
+        grammar.order.retain(|symbol| reachable.contains(symbol));
+        origins.retain(|(lhs, _), _| reachable.contains(lhs));
+        // %prec was given by the original rhs_ids
+        grammar.production_precedence.clear();
+        grammar.compute_sets();
+
+        Transformed
+        {
+            grammar,
+            origins,
+            previous: None
+        }
+    }
+}
+
+// a name for a new nonterminal, priming base until it is free
+fn fresh_nonterminal(grammar: &Grammar, rules: &Rules, base: &Symbol) -> Symbol
+{
+    let mut symbol = Symbol
+    {
+        label: format!("{}'", base.label),
+        terminal: false
+    };
+    while grammar.productions.contains_key(&symbol) || rules.contains_key(&symbol)
+    {
+        symbol.label.push('\'');
+    }
+    symbol
+}
+
+impl Grammar
+{
+    // Paull's algorithm: removes direct and indirect left recursion, including recursion hidden
+    // behind nullable symbols, by substituting leading non-terminals and moving what follows a
+    // directly recursive A into a right recursive tail A'. EBNF lists are left alone.
+    pub fn eliminate_left_recursion(&self) -> Result<Transformed, Error>
+    {
+        let grammar = self.right_recursive();
+        let is_list = |symbol: &Symbol| matches!(grammar.helpers.get(symbol), Some(Helper::Repeat { .. }) | Some(Helper::Optional { .. }));
+
+        let mut order = grammar.productions
+            .keys()
+            .filter(|symbol| !is_list(symbol) && **symbol != grammar.start)
+            .cloned()
+            .collect::<Vec<Symbol>>();
+        order.sort();
+        order.insert(0, grammar.start.clone());
+
+        let mut rules = order
+            .iter()
+            .map(|lhs| (lhs.clone(), grammar.productions[lhs]
+                .iter()
+                .enumerate()
+                .map(|(rhs_id, rhs)| (rhs.clone(), Template::identity(lhs, rhs_id as u32, rhs.len())))
+                .collect::<Vec<(Vec<Symbol>, Template)>>()))
+            .collect::<Rules>();
+        let mut tails = Vec::<Symbol>::new();
+
+        for (index, lhs) in order.iter().enumerate()
+        {
+            let earlier = &order[..index];
+            let mut work_list = rules.remove(lhs).unwrap().into_iter().collect::<VecDeque<(Vec<Symbol>, Template)>>();
+            let mut seen = HashSet::<Vec<Symbol>>::new();
+            let mut recursive = Vec::<(Vec<Symbol>, Template)>::new();
+            let mut others = Vec::<(Vec<Symbol>, Template)>::new();
+
+            while let Some((rhs, template)) = work_list.pop_front()
+            {
+                if !seen.insert(rhs.clone())
+                {
+                    continue;
+                }
+
+                if rhs.first() == Some(lhs)
+                {
+                    if grammar.rhs_derives_lambda(&rhs[1..].to_vec())
+                    {
+                        return Err(Error::Cycle(lhs.clone()));
+                    }
+                    recursive.push((rhs, template));
+                }
+                else if grammar.needs_substitution(&rhs, lhs, earlier, &rules)
+                {
+                    // in place of the production they replace, keeping the order of alternatives
+                    for (leading_rhs, leading_template) in rules[&rhs[0]].iter().rev()
+                    {
+                        let mut new_rhs = leading_rhs.clone();
+                        new_rhs.extend(rhs[1..].iter().cloned());
+                        work_list.push_front((new_rhs, template.substitute(0, leading_template, leading_rhs.len())));
+                    }
+                }
+                else
+                {
+                    others.push((rhs, template));
+                }
+            }
+
+            if recursive.is_empty()
+            {
+                rules.insert(lhs.clone(), others);
+                continue;
+            }
+
+            // A -> A alpha | beta  becomes  A -> beta A' ; A' -> alpha A' | lambda
+            let tail = fresh_nonterminal(&grammar, &rules, lhs);
+
+            let with_tail = |mut rhs: Vec<Symbol>, seed: Template| {
+                let template = Template::Continue
+                {
+                    seed: Box::new(seed),
+                    tail: rhs.len()
+                };
+                rhs.push(tail.clone());
+                (rhs, template)
+            };
+            let new_rules = others
+                .into_iter()
+                .map(|(rhs, template)| with_tail(rhs, template))
+                .collect::<Vec<(Vec<Symbol>, Template)>>();
+            let mut tail_rules = recursive
+                .into_iter()
+                .map(|(rhs, template)| with_tail(rhs[1..].to_vec(), template.substitute(0, &Template::Seed, 0)))
+                .collect::<Vec<(Vec<Symbol>, Template)>>();
+            tail_rules.push((vec![], Template::Seed));
+
+            rules.insert(lhs.clone(), new_rules);
+            rules.insert(tail.clone(), tail_rules);
+            tails.push(tail);
+        }
+
+        Ok(Transformed::from_rules(grammar, rules, tails))
+    }
+
+    // rewrites A -> a b | a c into A -> a A' ; A' -> b | c, over and over until no two
+    // alternatives of a nonterminal start alike. EBNF lists are left alone.
+    pub fn left_factor(&self) -> Transformed
+    {
+        let is_list = |symbol: &Symbol| matches!(self.helpers.get(symbol), Some(Helper::Repeat { .. }) | Some(Helper::Optional { .. }));
+
+        let mut work_list = self.productions
+            .keys()
+            .filter(|symbol| !is_list(symbol))
+            .cloned()
+            .collect::<Vec<Symbol>>();
+        work_list.sort();
+        work_list.reverse();
+
+        let mut rules = work_list
+            .iter()
+            .map(|lhs| (lhs.clone(), self.productions[lhs]
+                .iter()
+                .enumerate()
+                .map(|(rhs_id, rhs)| (rhs.clone(), Template::identity(lhs, rhs_id as u32, rhs.len())))
+                .collect::<Vec<(Vec<Symbol>, Template)>>()))
+            .collect::<Rules>();
+        // how many children a factored nonterminal is handed by its parent
+        let mut inherited = HashMap::<Symbol, usize>::new();
+        let mut factored = Vec::<Symbol>::new();
+
+        while let Some(lhs) = work_list.pop()
+        {
+            let mut prod_list = rules.remove(&lhs).unwrap();
+            let mut index = 0;
+            while index < prod_list.len()
+            {
+                let group = (index..prod_list.len())
+                    .filter(|other| !prod_list[*other].0.is_empty() && prod_list[*other].0.first() == prod_list[index].0.first())
+                    .collect::<Vec<usize>>();
+                if group.len() < 2
+                {
+                    index += 1;
+                    continue;
+                }
+
+                let first = &prod_list[group[0]].0;
+                let prefix_len = (1..first.len())
+                    .take_while(|length| group.iter().all(|other| prod_list[*other].0.len() > *length && prod_list[*other].0[*length] == first[*length]))
+                    .count() + 1;
+                let prefix = first[..prefix_len].to_vec();
+
+                let new_symbol = fresh_nonterminal(self, &rules, &lhs);
+                let passed = inherited.get(&lhs).copied().unwrap_or(0) + prefix_len;
+                let mut new_rules = Vec::<(Vec<Symbol>, Template)>::new();
+                for other in group.iter().rev()
+                {
+                    let (rhs, template) = prod_list.remove(*other);
+                    new_rules.push((rhs[prefix_len..].to_vec(), template));
+                }
+                new_rules.reverse();
+
+                let mut rhs = prefix;
+                rhs.push(new_symbol.clone());
+                prod_list.insert(index, (rhs, Template::Pass { tail: passed }));
+
+                rules.insert(new_symbol.clone(), new_rules);
+                inherited.insert(new_symbol.clone(), passed);
+                factored.push(new_symbol.clone());
+                work_list.push(new_symbol);
+                index += 1;
+            }
+            rules.insert(lhs, prod_list);
+        }
+
+        Transformed::from_rules(self.clone(), rules, factored)
+    }
+
+    // drops the productions that use unproductive or undefined nonterminals, then whatever is left
+    // unreachable; the start symbol stays, without productions if it derives nothing
+    pub fn remove_useless(&self) -> Transformed
+    {
+        let productive = self.productive_symbols();
+
+        let rules = self.productions
+            .iter()
+            .filter(|(lhs, _)| productive.contains(lhs) || **lhs == self.start)
+            .map(|(lhs, prod_list)| (lhs.clone(), prod_list
+                .iter()
+                .enumerate()
+                .filter(|(_, rhs)| rhs.iter().all(|symbol| symbol.terminal || productive.contains(symbol)))
+                .map(|(rhs_id, rhs)| (rhs.clone(), Template::identity(lhs, rhs_id as u32, rhs.len())))
+                .collect::<Vec<(Vec<Symbol>, Template)>>()))
+            .collect::<Rules>();
+
+        let mut grammar = self.clone();
+        grammar.productions.retain(|lhs, _| rules.contains_key(lhs));
+        Transformed::from_rules(grammar, rules, vec![])
+    }
+
+    // whether the leading symbol of a production of lhs has to be replaced by its productions:
+    // it was handled before lhs, or it is nullable and hides such a symbol, or lhs itself, behind it
+    fn needs_substitution(&self, rhs: &[Symbol], lhs: &Symbol, earlier: &[Symbol], rules: &HashMap<Symbol, Vec<(Vec<Symbol>, Template)>>) -> bool
+    {
+        let first = match rhs.first()
+        {
+            Some(first) if rules.contains_key(first) => first,
+            _ => return false
+        };
+        if earlier.contains(first)
+        {
+            return true;
+        }
+
+        if self.lambda_deriving_symbols().contains(first)
+        {
+            for symbol in &rhs[1..]
+            {
+                if symbol == lhs || earlier.contains(symbol)
+                {
+                    return true;
+                }
+                if !self.lambda_deriving_symbols().contains(symbol)
+                {
+                    break;
+                }
+            }
+        }
+        false
+    }
+}
+
+#[test]
+fn test_direct_left_recursion()
+{
+    use crate::ll_parser::LLParser;
+    use crate::lr_parser::{LRParser, Mode};
+
+    let grammar = Grammar::from_file("data/self_referencing").unwrap();
+    let transformed = grammar.eliminate_left_recursion().unwrap();
+    let e = Symbol::from(String::from("E"));
+    let tail = Symbol::from(String::from("E'"));
+
+    // E -> num E' ; E' -> e E' | lambda
+    assert_eq!(transformed.grammar.productions[&e], vec![vec![Symbol::from(String::from("num")), tail.clone()]]);
+    assert_eq!(transformed.grammar.productions[&tail].len(), 2);
+    assert!(transformed.grammar.lambda_deriving_symbols().contains(&tail));
+
+    let program = String::from("num e e $");
+    let ll_tree = LLParser::new(transformed.grammar.clone()).unwrap().parse(program.clone()).unwrap();
+    let lr_tree = LRParser::new(grammar, Mode::SLR).unwrap().parse(program).unwrap();
+    assert_eq!(transformed.rebuild(&ll_tree), lr_tree);
+}
+
+#[test]
+fn test_indirect_left_recursion()
+{
+    use crate::ll_parser::LLParser;
+    use crate::lr_parser::{LRParser, Mode};
+
+    let grammar = Grammar::from_file("data/indirect").unwrap();
+    let transformed = grammar.eliminate_left_recursion().unwrap();
+
+    for (lhs, prod_list) in &transformed.grammar.productions
+    {
+        assert!(prod_list.iter().all(|rhs| rhs.first() != Some(lhs)), "{} is still left recursive", lhs);
+    }
+
+    let program = String::from("w x z x z x $");
+    let ll_tree = LLParser::new(transformed.grammar.clone()).unwrap().parse(program.clone()).unwrap();
+    let lr_tree = LRParser::new(grammar, Mode::LALR).unwrap().parse(program).unwrap();
+    assert_eq!(transformed.rebuild(&ll_tree), lr_tree);
+}
+
+#[test]
+fn test_hidden_left_recursion()
+{
+    // the nullable Sign hides A -> A b behind it
+    let grammar = "Start -> A '$' ; A -> Sign A b | a ; Sign -> minus | ;".parse::<Grammar>().unwrap();
+    let transformed = grammar.eliminate_left_recursion().unwrap();
+    let symbol = |label: &str| Symbol::from(String::from(label));
+
+    // A -> minus A b A' | a A' ; A' -> b A' | lambda
+    assert_eq!(transformed.grammar.productions[&symbol("A")], vec![
+        vec![symbol("minus"), symbol("A"), symbol("b"), symbol("A'")],
+        vec![symbol("a"), symbol("A'")]
+    ]);
+    assert_eq!(transformed.grammar.productions[&symbol("A'")], vec![vec![symbol("b"), symbol("A'")], vec![]]);
+
+    // the tail remembers the lambda production of Sign it stands in for
+    assert_eq!(transformed.origin(&symbol("A'"), 0), Some(&Template::Continue
+    {
+        seed: Box::new(Template::Node
+        {
+            lhs: symbol("A"),
+            rhs_id: 0,
+            children: vec![Template::Node { lhs: symbol("Sign"), rhs_id: 1, children: vec![] }, Template::Seed, Template::Child(0)]
+        }),
+        tail: 1
+    }));
+
+    let cyclic = "Start -> A '$' ; A -> A Sign | a ; Sign -> minus | ;".parse::<Grammar>().unwrap();
+    assert_eq!(cyclic.eliminate_left_recursion().unwrap_err(), Error::Cycle(symbol("A")));
+}
+
+#[test]
+fn test_left_factoring()
+{
+    use crate::ll_parser::LLParser;
+    use crate::lr_parser::{LRParser, Mode};
+
+    let grammar = Grammar::from_file("data/factor").unwrap();
+    let transformed = grammar.left_factor();
+    let symbol = |label: &str| Symbol::from(String::from(label));
+
+    // Stmt -> id Stmt' | print Expr semi ; Stmt' -> assign Expr semi | lp Stmt'' ; Stmt'' -> rp semi | Expr rp semi
+    let productions = &transformed.grammar.productions;
+    assert_eq!(productions[&symbol("Stmt")], vec![vec![symbol("id"), symbol("Stmt'")], vec![symbol("print"), symbol("Expr"), symbol("semi")]]);
+    assert_eq!(productions[&symbol("Stmt'")][1], vec![symbol("lp"), symbol("Stmt''")]);
+    assert_eq!(productions[&symbol("Stmt''")], vec![vec![symbol("rp"), symbol("semi")], vec![symbol("Expr"), symbol("rp"), symbol("semi")]]);
+    assert_eq!(productions[&symbol("Expr'")], vec![vec![], vec![symbol("plus"), symbol("Expr")]]);
+
+    // the innermost alternative is reported as the user's Stmt -> id lp Expr rp semi
+    assert_eq!(transformed.origin(&symbol("Stmt''"), 1), Some(&Template::identity(&symbol("Stmt"), 2, 5)));
+
+    let ll_parser = LLParser::new(transformed.grammar.clone()).unwrap();
+    let lr_parser = LRParser::new(grammar, Mode::LALR).unwrap();
+    for program in &["id assign num plus num semi $", "id lp rp semi $", "id lp num plus num rp semi $", "print num semi $"]
+    {
+        let ll_tree = ll_parser.parse(program.to_string()).unwrap();
+        assert_eq!(transformed.rebuild(&ll_tree), lr_parser.parse(program.to_string()).unwrap(), "{}", program);
+    }
+
+    // P -> id | lp E rp | id assign E
+    let factored = Grammar::from_file("data/10a").unwrap().left_factor().grammar;
+    assert_eq!(factored.productions[&symbol("P")], vec![vec![symbol("id"), symbol("P'")], vec![symbol("lp"), symbol("E"), symbol("rp")]]);
+    assert_eq!(factored.productions[&symbol("P'")], vec![vec![], vec![symbol("assign"), symbol("E")]]);
+}
+
+#[test]
+fn test_chained_transformations()
+{
+    use crate::ll_parser::LLParser;
+    use crate::lr_parser::{LRParser, Mode};
+
+    let grammar = "Start -> E '$' ; E -> E plus T | T ; T -> id | id lp E rp ;".parse::<Grammar>().unwrap();
+    let transformed = grammar.eliminate_left_recursion().unwrap().left_factor();
+
+    let program = String::from("id plus id lp id plus id rp plus id $");
+    let ll_tree = LLParser::new(transformed.grammar.clone()).unwrap().parse(program.clone()).unwrap();
+    let lr_tree = LRParser::new(grammar, Mode::LALR).unwrap().parse(program).unwrap();
+    assert_eq!(transformed.rebuild(&ll_tree), lr_tree);
+}
+
+#[test]
+fn test_remove_useless()
+{
+    use crate::lr_parser::{LRParser, Mode};
+
+    let grammar = "Start -> B '$' | A '$' ; A -> a | A a ; B -> B b ; C -> c ;".parse::<Grammar>().unwrap();
+    let transformed = grammar.remove_useless();
+    let symbol = |label: &str| Symbol::from(String::from(label));
+
+    assert!(transformed.grammar.analyze().is_clean());
+    assert_eq!(transformed.grammar.productions[&symbol("Start")], vec![vec![symbol("A"), symbol("$")]]);
+    assert!(!transformed.grammar.terminals.contains(&symbol("c")));
+
+    // Start -> A $ is still reported as the user's second production
+    let tree = LRParser::new(transformed.grammar.clone(), Mode::SLR).unwrap().parse(String::from("a a $")).unwrap();
+    assert!(matches!(transformed.rebuild(&tree), ParseTree::Node { rhs_id: 1, .. }));
+}
@@ -0,0 +1,375 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use crate::symbol::Symbol;
+use crate::grammar::{Associativity, Grammar, Helper};
+
+// The grammar file format, which reads back to an equal grammar: declarations, then the rules
+// in declaration order with their EBNF as written. Names the format cannot spell, like the
+// lowercase non-terminals a GrammarBuilder allows, do not read back.
+impl Display for Grammar
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        let rules = self.rules();
+
+        let mut tokens = self.declared_tokens
+            .iter()
+            .map(|name| terminal(name))
+            .filter(|symbol| !self.precedence.contains_key(symbol))
+            .collect::<Vec<Symbol>>();
+        tokens.sort();
+        if !tokens.is_empty()
+        {
+            writeln!(f, "%token {} ;", self.source_texts(&tokens))?;
+        }
+        for (associativity, symbols) in self.precedence_levels()
+        {
+            let keyword = match associativity
+            {
+                Associativity::Left => "%left",
+                Associativity::Right => "%right",
+                Associativity::NonAssoc => "%nonassoc"
+            };
+            writeln!(f, "{} {} ;", keyword, self.source_texts(&symbols))?;
+        }
+        let mut named = self.display_names.iter().collect::<Vec<(&Symbol, &String)>>();
+        named.sort();
+        if !named.is_empty()
+        {
+            let names = named
+                .iter()
+                .map(|(symbol, name)| format!("{} \"{}\"", self.source_text(symbol), name.replace('\\', "\\\\").replace('"', "\\\"")))
+                .collect::<Vec<String>>();
+            writeln!(f, "%name {} ;", names.join(" "))?;
+        }
+        // the reader's default: Start if there is one, otherwise the first rule
+        let default_start = match rules.iter().any(|lhs| lhs.label == "Start")
+        {
+            true => Some(String::from("Start")),
+            false => rules.first().map(|lhs| lhs.label.clone())
+        };
+        let declare_start = default_start.as_ref() != Some(&self.start.label);
+        if declare_start
+        {
+            writeln!(f, "%start {} ;", self.source_text(&self.start))?;
+        }
+        if !tokens.is_empty() || !self.precedence.is_empty() || !named.is_empty() || declare_start
+        {
+            writeln!(f)?;
+        }
+
+        for lhs in rules
+        {
+            for (rhs_id, rhs) in self.productions[&lhs].iter().enumerate()
+            {
+                let mut line = match rhs_id
+                {
+                    0 => format!("{} ->", self.source_text(&lhs)),
+                    _ => String::from("    |")
+                };
+                if !rhs.is_empty()
+                {
+                    line = format!("{} {}", line, self.source_texts(rhs));
+                }
+                if let Some(prec) = self.production_precedence.get(&(lhs.clone(), rhs_id as u32))
+                {
+                    line = format!("{} %prec {}", line, self.source_text(prec));
+                }
+                if rhs_id + 1 == self.productions[&lhs].len()
+                {
+                    line.push_str(" ;");
+                }
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn terminal(name: &str) -> Symbol
+{
+    Symbol
+    {
+        label: name.to_string(),
+        terminal: true
+    }
+}
+
+pub(crate) fn is_identifier(text: &str) -> bool
+{
+    text.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Grammar
+{
+    // the lhs symbols that are not EBNF helpers, in declaration order
+    fn rules(&self) -> Vec<Symbol>
+    {
+        self.rule_order()
+            .into_iter()
+            .filter(|lhs| !self.helpers.contains_key(lhs))
+            .collect()
+    }
+
+    fn source_texts(&self, symbols: &[Symbol]) -> String
+    {
+        symbols.iter().map(|symbol| self.source_text(symbol)).collect::<Vec<String>>().join(" ")
+    }
+
+    // one entry per precedence level, lowest first, its terminals sorted
+    fn precedence_levels(&self) -> Vec<(Associativity, Vec<Symbol>)>
+    {
+        let mut levels = self.precedence.values().map(|(level, _)| *level).collect::<Vec<u32>>();
+        levels.sort();
+        levels.dedup();
+
+        levels
+            .into_iter()
+            .map(|level| {
+                let mut symbols = self.precedence
+                    .iter()
+                    .filter(|(_, (other, _))| *other == level)
+                    .map(|(symbol, _)| symbol.clone())
+                    .collect::<Vec<Symbol>>();
+                symbols.sort();
+                (self.precedence[&symbols[0]].1, symbols)
+            })
+            .collect()
+    }
+
+    // W3C EBNF, as in the XML specification, with the helpers written back as EBNF;
+    // terminals that are not plain names are quoted, and precedence is left out
+    pub fn to_ebnf(&self) -> String
+    {
+        let mut out = String::new();
+        for lhs in self.rules()
+        {
+            let prod_list = &self.productions[&lhs];
+            let alternatives = prod_list
+                .iter()
+                .filter(|rhs| !rhs.is_empty())
+                .map(|rhs| rhs.iter().map(|symbol| self.ebnf_text(symbol)).collect::<Vec<String>>().join(" "))
+                .collect::<Vec<String>>();
+            let indent = " ".repeat(lhs.label.len() + 1);
+
+            // W3C EBNF has no empty alternative, so a nullable rule becomes optional
+            let body = if alternatives.is_empty()
+            {
+                String::from("''")
+            }
+            else if alternatives.len() < prod_list.len()
+            {
+                format!("( {} )?", alternatives.join(&format!("\n{}  | ", indent)))
+            }
+            else
+            {
+                alternatives.join(&format!("\n{}| ", indent))
+            };
+            out.push_str(&format!("{} ::= {}\n", lhs.label, body));
+        }
+        out
+    }
+
+    fn ebnf_text(&self, symbol: &Symbol) -> String
+    {
+        // the operand of *, + or ? has to be a name, literal or parenthesized group
+        let operand = |element: &Symbol| match self.helpers.get(element)
+        {
+            Some(Helper::Repeat { .. }) | Some(Helper::Optional { .. }) => format!("({})", self.ebnf_text(element)),
+            _ => self.ebnf_text(element)
+        };
+
+        match self.helpers.get(symbol)
+        {
+            Some(Helper::Repeat { element, at_least_one }) => format!("{}{}", operand(element), if *at_least_one { "+" } else { "*" }),
+            Some(Helper::Optional { element }) => format!("{}?", operand(element)),
+            Some(Helper::Group) => format!("({})", self.productions[symbol]
+                .iter()
+                .map(|rhs| rhs.iter().map(|symbol| self.ebnf_text(symbol)).collect::<Vec<String>>().join(" "))
+                .collect::<Vec<String>>()
+                .join(" | ")),
+            None if !symbol.terminal || is_identifier(&symbol.label) => symbol.label.clone(),
+            None if symbol.label.contains('\'') => format!("\"{}\"", symbol.label),
+            None => format!("'{}'", symbol.label)
+        }
+    }
+
+    // a yacc/bison .y file of the rules, with the EBNF helpers as rules of their own named helper1, helper2 ...
+    // Terminals of one character are character literals, plain names are declared with %token, and the
+    // rest become bison string aliases. There are no actions, and the grammar's own end marker stays a token.
+    pub fn to_yacc(&self) -> String
+    {
+        let names = self.yacc_names();
+        let name = |symbol: &Symbol| names[symbol].clone();
+
+        let mut out = String::new();
+        let mut tokens = self.terminals
+            .iter()
+            .chain(self.precedence.keys())
+            .filter(|symbol| symbol.label.chars().count() > 1)
+            .cloned()
+            .collect::<HashSet<Symbol>>()
+            .into_iter()
+            .collect::<Vec<Symbol>>();
+        tokens.sort();
+        for (index, symbol) in tokens.iter().enumerate()
+        {
+            match is_identifier(&symbol.label)
+            {
+                true => out.push_str(&format!("%token {}\n", symbol.label)),
+                false => out.push_str(&format!("%token TOKEN{} {}\n", index + 1, name(symbol)))
+            }
+        }
+        for (associativity, symbols) in self.precedence_levels()
+        {
+            let keyword = match associativity
+            {
+                Associativity::Left => "%left",
+                Associativity::Right => "%right",
+                Associativity::NonAssoc => "%nonassoc"
+            };
+            out.push_str(&format!("{} {}\n", keyword, symbols.iter().map(name).collect::<Vec<String>>().join(" ")));
+        }
+        out.push_str(&format!("%start {}\n\n%%\n", name(&self.start)));
+
+        let (rules, helpers): (Vec<Symbol>, Vec<Symbol>) = self.rule_order()
+            .into_iter()
+            .partition(|lhs| !self.helpers.contains_key(lhs));
+        for lhs in rules.iter().chain(&helpers)
+        {
+            out.push('\n');
+            match self.helpers.contains_key(lhs)
+            {
+                true => out.push_str(&format!("{} /* {} */\n", name(lhs), lhs.label)),
+                false => out.push_str(&format!("{}\n", name(lhs)))
+            }
+            for (rhs_id, rhs) in self.productions[lhs].iter().enumerate()
+            {
+                let mut line = match rhs.is_empty()
+                {
+                    true => String::from("/* empty */"),
+                    false => rhs.iter().map(name).collect::<Vec<String>>().join(" ")
+                };
+                if let Some(prec) = self.production_precedence.get(&(lhs.clone(), rhs_id as u32))
+                {
+                    line = format!("{} %prec {}", line, name(prec));
+                }
+                out.push_str(&format!("    {} {}\n", if rhs_id == 0 { ":" } else { "|" }, line));
+            }
+            out.push_str("    ;\n");
+        }
+        out.push_str("\n%%\n");
+        out
+    }
+
+    // how each symbol is spelled in yacc; non-terminals that are not identifiers, like the E'
+    // of eliminate_left_recursion, are renamed apart from the names already in use
+    fn yacc_names(&self) -> HashMap<Symbol, String>
+    {
+        let mut names = HashMap::<Symbol, String>::new();
+        let mut used = self.nonterminals
+            .iter()
+            .chain(self.productions.keys())
+            .filter(|symbol| is_identifier(&symbol.label))
+            .map(|symbol| symbol.label.clone())
+            .collect::<HashSet<String>>();
+
+        for symbol in self.terminals.iter().chain(self.precedence.keys())
+        {
+            let label = symbol.label.replace('\\', "\\\\");
+            let text = match symbol.label.chars().count()
+            {
+                1 => format!("'{}'", label.replace('\'', "\\'")),
+                _ if is_identifier(&symbol.label) => symbol.label.clone(),
+                _ => format!("\"{}\"", label.replace('"', "\\\""))
+            };
+            names.insert(symbol.clone(), text);
+        }
+
+        let mut nonterminals = self.nonterminals
+            .iter()
+            .chain(self.productions.keys())
+            .chain(std::iter::once(&self.start))
+            .cloned()
+            .collect::<HashSet<Symbol>>()
+            .into_iter()
+            .collect::<Vec<Symbol>>();
+        let order = self.rule_order();
+        nonterminals.sort_by_key(|symbol| (order.iter().position(|lhs| lhs == symbol), symbol.clone()));
+        let mut helper_count = 0;
+        for symbol in nonterminals
+        {
+            let base = if self.helpers.contains_key(&symbol)
+            {
+                helper_count += 1;
+                format!("helper{}", helper_count)
+            }
+            else if is_identifier(&symbol.label)
+            {
+                names.insert(symbol.clone(), symbol.label.clone());
+                continue;
+            }
+            else
+            {
+                symbol.label
+                    .replace('\'', "_prime")
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                    .collect::<String>()
+            };
+
+            let mut name = base.clone();
+            let mut suffix = 1;
+            while used.contains(&name)
+            {
+                suffix += 1;
+                name = format!("{}{}", base, suffix);
+            }
+            used.insert(name.clone());
+            names.insert(symbol, name);
+        }
+        names
+    }
+}
+
+#[test]
+fn test_round_trip()
+{
+    for filename in &["data/10a", "data/bnf", "data/eeeee", "data/self_referencing", "data/lr1", "data/lalr", "data/calc", "data/ebnf", "data/precedence", "data/indirect", "data/factor", "data/ll3"]
+    {
+        let grammar = Grammar::from_file(filename).unwrap();
+        let text = grammar.to_string();
+        let read = text.parse::<Grammar>().unwrap();
+
+        assert_eq!(read, grammar, "{}", filename);
+        assert_eq!(read.to_string(), text, "{}", filename);
+    }
+
+    let grammar = Grammar::from_file("data/calc").unwrap();
+    assert_eq!(grammar.to_string(), "%token NUM ;\n\nProgram -> Expr $ ;\nExpr -> Expr '+' Term\n    | Term ;\nTerm -> Term '*' Factor\n    | Factor ;\nFactor -> '(' Expr ')'\n    | NUM ;\n");
+
+    // the primed non-terminals the transforms add read back too
+    let grammar = "Start -> E $ ;\nE -> E \"+\" n | n ;".parse::<Grammar>().unwrap().eliminate_left_recursion().unwrap().grammar;
+    let text = grammar.to_string();
+    assert_eq!(text, "Start -> E $ ;\nE -> n E' ;\nE' -> '+' n E'\n    | ;\n");
+    assert_eq!(text.parse::<Grammar>().unwrap().productions, grammar.productions);
+    let grammar = Grammar::from_file("data/factor").unwrap().left_factor().grammar;
+    assert_eq!(grammar.to_string().parse::<Grammar>().unwrap().productions, grammar.productions);
+    let grammar = "S -> (A' | b) A'' c';' ; A' -> a ; A'' -> ;".parse::<Grammar>().unwrap();
+    assert_eq!(grammar.to_string(), "S -> (A' | b) A'' c ';' ;\nA' -> a ;\nA'' -> ;\n");
+}
+
+#[test]
+fn test_ebnf_and_yacc()
+{
+    let grammar = "%left '+' ; List -> '[' (Item (',' Item)*)? ']' ; Item -> x | Item '+' Item | ;".parse::<Grammar>().unwrap();
+
+    assert_eq!(grammar.to_ebnf(), "List ::= '[' (Item (',' Item)*)? ']'\nItem ::= ( x\n       | Item '+' Item )?\n");
+
+    let yacc = grammar.to_yacc();
+    assert!(yacc.starts_with("%left '+'\n%start List\n\n%%\n\nList\n    : '[' helper4 ']'\n"));
+    assert!(yacc.contains("\nItem\n    : 'x'\n    | Item '+' Item\n    | /* empty */\n    ;\n"));
+    assert!(yacc.contains("\nhelper2 /* (, Item)* */\n    : helper2 helper1\n"));
+    assert!(yacc.ends_with("    ;\n\n%%\n"));
+}